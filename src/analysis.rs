@@ -0,0 +1,262 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Instant;
+
+use crate::bot::{MachinePlayer, SearchBudget, N_PARAMETERS};
+use crate::card::card_mask;
+use crate::game::{GameState, Position, SafeGameInterface};
+use crate::mcts;
+use crate::play::Play;
+
+// ranks every legal play for the player to move in a position, so that a game can be looked over
+// afterwards. higher scores are always better, but what they mean depends on the evaluator
+
+pub enum Evaluator {
+    /// The search that `MachinePlayer` does. Scores are minus the expected number of times
+    /// we will have to pass, and only our own hand is looked at
+    Heuristic([f64; N_PARAMETERS]),
+    /// Playouts with the hidden cards dealt at random. Scores are the average result,
+    /// from 0 for coming last to 1 for going out first. `node_limit` is the number of playouts
+    Mcts { seed: u64 },
+    /// Looks at every way the rest of the game could go, with all the hands as they are in the position,
+    /// and the other players all working against us. Scores are 1 if we can go out before everyone
+    /// else no matter what, and 0 otherwise. `node_limit` is how many positions it may look at
+    Exact,
+}
+
+impl Evaluator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Evaluator::Heuristic(_) => "heuristic",
+            Evaluator::Mcts { .. } => "mcts",
+            Evaluator::Exact => "exact",
+        }
+    }
+}
+
+// unless the budget says otherwise
+const EXACT_NODE_LIMIT: usize = 2_000_000;
+
+/// Scores every legal play for the player to move in `position`, best first
+pub fn analyze(
+    position: &Position,
+    evaluator: &Evaluator,
+    budget: SearchBudget,
+) -> Result<Vec<(Play, f64)>, String> {
+    let game = GameState::from_position(position)?;
+    analyze_game(&game, evaluator, budget)
+}
+
+/// The same as `analyze`, for a game that is already under way
+pub fn analyze_game(
+    game: &GameState,
+    evaluator: &Evaluator,
+    budget: SearchBudget,
+) -> Result<Vec<(Play, f64)>, String> {
+    if game.is_over() {
+        return Err("the game is already over".to_string());
+    }
+
+    let interface = SafeGameInterface::from_game(game);
+
+    let mut scored = match evaluator {
+        Evaluator::Heuristic(parameters) => heuristic(&interface, *parameters, budget),
+        Evaluator::Mcts { seed } => {
+            let iterations = budget.node_limit.unwrap_or(mcts::DEFAULT_ITERATIONS);
+            let mut rng = StdRng::seed_from_u64(*seed);
+
+            mcts::search(&interface, iterations, budget.time_limit, &mut rng)
+                .into_iter()
+                .map(|s| {
+                    let score = s.mean_reward();
+                    (s.play, score)
+                })
+                .collect()
+        }
+        Evaluator::Exact => exact(game, budget)?,
+    };
+
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Ok(scored)
+}
+
+/// The result of `analyze`, ready to be shown
+#[derive(Debug, Serialize)]
+pub struct AnalysisReport {
+    pub evaluator: String,
+    pub seat: usize,
+    pub plays: Vec<ScoredPlay>, // best first
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScoredPlay {
+    pub play: Play,
+    pub score: f64,
+}
+
+impl AnalysisReport {
+    pub fn new(evaluator: &Evaluator, seat: usize, scored: Vec<(Play, f64)>) -> AnalysisReport {
+        AnalysisReport {
+            evaluator: evaluator.name().to_string(),
+            seat,
+            plays: scored
+                .into_iter()
+                .map(|(play, score)| ScoredPlay { play, score })
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl fmt::Display for AnalysisReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} analysis for seat {}", self.evaluator, self.seat)?;
+        writeln!(f, "{:>4}  {:<20} {:>10}", "rank", "play", "score")?;
+
+        for (rank, scored) in self.plays.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>4}  {:<20} {:>10.3}",
+                rank + 1,
+                scored.play.to_string(),
+                scored.score
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn heuristic(
+    game: &SafeGameInterface,
+    parameters: [f64; N_PARAMETERS],
+    budget: SearchBudget,
+) -> Vec<(Play, f64)> {
+    let (_play, report) =
+        MachinePlayer::with_budget(parameters, budget).choose_play_with_report(game);
+
+    // the search plans sequences out of everything in our hand, and not everything it plans can be played
+    // right now. passing is worth the best plan that starts with something we can't play yet
+    let mut pass_cost = None;
+    let mut costs = HashMap::new();
+    for candidate in report.candidates.iter() {
+        if game.can_play(candidate.play.cards().to_vec()).is_ok() {
+            costs.insert(card_mask(candidate.play.cards()), candidate.mean_cost);
+        } else if pass_cost.is_none() {
+            pass_cost = Some(candidate.mean_cost); // they are sorted best first
+        }
+    }
+
+    let scores: Vec<(Play, Option<f64>)> = game
+        .legal_plays()
+        .into_iter()
+        .map(|play| {
+            let cost = if play.is_pass() {
+                pass_cost
+            } else {
+                costs.get(&card_mask(play.cards())).cloned()
+            };
+            (play, cost.filter(|c| c.is_finite()).map(|c| -c))
+        })
+        .collect();
+
+    // anything the search didn't get to goes at the bottom
+    let worst = scores
+        .iter()
+        .filter_map(|s| s.1)
+        .min_by(f64::total_cmp)
+        .unwrap_or(0.0);
+
+    scores
+        .into_iter()
+        .map(|(play, score)| (play, score.unwrap_or(worst)))
+        .collect()
+}
+
+fn exact(game: &GameState, budget: SearchBudget) -> Result<Vec<(Play, f64)>, String> {
+    let mut solver = Solver {
+        me: game.current_player,
+        n_finished: game.finished().len(),
+        nodes_left: budget.node_limit.unwrap_or(EXACT_NODE_LIMIT),
+        deadline: budget.time_limit.map(|t| Instant::now() + t),
+        memo: HashMap::new(),
+    };
+
+    let mut game = GameState::from_position(&game.position())?;
+    let mut scored = Vec::new();
+
+    for play in game.legal_plays() {
+        game.play(play.clone());
+        let wins = solver.wins(&mut game);
+        game.undo();
+
+        let wins = wins.ok_or_else(|| {
+            "too many positions to look at every one, try a bigger budget or mcts".to_string()
+        })?;
+        scored.push((play, if wins { 1.0 } else { 0.0 }));
+    }
+
+    Ok(scored)
+}
+
+// everything that matters about a position to the solver. which plays led up to it don't
+type Key = (Vec<u64>, usize, u64, Option<usize>, bool);
+
+struct Solver {
+    me: usize,
+    n_finished: usize, // when we started
+    nodes_left: usize,
+    deadline: Option<Instant>,
+    memo: HashMap<Key, bool>,
+}
+
+impl Solver {
+    // whether `me` goes out before anyone else still playing, with everyone else against us.
+    // None once we have run out of budget
+    fn wins(&mut self, game: &mut GameState) -> Option<bool> {
+        if game.finished().len() > self.n_finished {
+            return Some(game.finished()[self.n_finished] == self.me);
+        }
+
+        let key = (
+            game.hands.iter().map(|h| card_mask(h)).collect(),
+            game.current_player,
+            game.cards_down().map_or(0, card_mask),
+            game.table_owner(),
+            game.have_control(),
+        );
+        if let Some(&known) = self.memo.get(&key) {
+            return Some(known);
+        }
+
+        if self.nodes_left == 0 || self.deadline.is_some_and(|d| Instant::now() >= d) {
+            return None;
+        }
+        self.nodes_left -= 1;
+
+        // we need one play that wins, they need one play that stops us
+        let ours = game.current_player == self.me;
+        let mut result = !ours;
+
+        for play in game.legal_plays() {
+            game.play(play);
+            let wins = self.wins(game);
+            game.undo();
+
+            if wins? == ours {
+                result = ours;
+                break;
+            }
+        }
+
+        self.memo.insert(key, result);
+        Some(result)
+    }
+}
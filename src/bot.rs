@@ -1,14 +1,23 @@
 use ordered_float::OrderedFloat;
 
 use std::cmp::min;
-use std::collections::{HashMap, HashSet};
-use std::f64::INFINITY;
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::fmt;
 use std::io;
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::card::{Card, Rank, THREE_OF_CLUBS};
+use crate::card::{card_mask, parse_cards, THREE_OF_CLUBS};
+use crate::control::{ControlTable, Situation};
 use crate::game::SafeGameInterface;
+use crate::opponent::Opponents;
 use crate::play::finder::Finder;
 use crate::play::{Play, PlayKind};
+use crate::report::{BranchUse, CandidateReport, DecisionReport};
+use crate::runner;
 
 pub const N_PARAMETERS: usize = 10;
 
@@ -29,12 +38,19 @@ impl Player for HumanPlayer {
             your_hand.sort();
             println!("your turn - {:?}", your_hand);
             let mut cards_string = String::new();
-            io::stdin().read_line(&mut cards_string).unwrap();
+            if io::stdin().read_line(&mut cards_string).unwrap() == 0 {
+                // stdin is closed, so nobody is ever going to answer
+                eprintln!("no more input, leaving the game");
+                process::exit(1);
+            }
 
-            let cards: Vec<Card> = cards_string
-                .split_whitespace()
-                .map(|c| c.parse().unwrap())
-                .collect();
+            let cards = match parse_cards(&cards_string) {
+                Ok(cards) => cards,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    continue;
+                }
+            };
 
             // try to play these cards
             match game.can_play(cards) {
@@ -51,211 +67,568 @@ impl Player for HumanPlayer {
     }
 }
 
+/// How much work `MachinePlayer` is allowed to do for a single decision
+#[derive(Clone, Copy, Debug)]
+pub struct SearchBudget {
+    pub max_depth: usize, // in cards, not plays
+    pub time_limit: Option<Duration>,
+    pub node_limit: Option<usize>,
+}
+
+impl Default for SearchBudget {
+    fn default() -> SearchBudget {
+        SearchBudget {
+            max_depth: 6,
+            time_limit: None,
+            node_limit: None,
+        }
+    }
+}
+
 pub struct MachinePlayer {
     constants: [f64; N_PARAMETERS],
+    budget: SearchBudget,
+    control: Arc<ControlTable>, // how likely each play is to win its trick
+    opponents: Option<Arc<Opponents>>, // how the people at the table tend to play
+    seats: Vec<String>,                // and who is sitting where
 }
 
 impl MachinePlayer {
     pub fn new(constants: [f64; N_PARAMETERS]) -> MachinePlayer {
-        MachinePlayer { constants }
+        MachinePlayer::with_budget(constants, SearchBudget::default())
     }
-}
 
-impl Player for MachinePlayer {
-    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+    pub fn with_budget(constants: [f64; N_PARAMETERS], budget: SearchBudget) -> MachinePlayer {
+        MachinePlayer {
+            constants,
+            budget,
+            control: ControlTable::built_in(),
+            opponents: None,
+            seats: Vec::new(),
+        }
+    }
+
+    /// Uses `control` instead of the table that ships with the program
+    pub fn with_control(mut self, control: Arc<ControlTable>) -> MachinePlayer {
+        self.control = control;
+        self
+    }
+
+    /// Makes our plays more or less likely to win their tricks, depending on how often
+    /// the others still in the game pass when they could play. `seats` are their names,
+    /// and any seat left out goes by the name it has in saved games
+    pub fn with_opponents(
+        mut self,
+        opponents: Arc<Opponents>,
+        seats: Vec<String>,
+    ) -> MachinePlayer {
+        self.opponents = Some(opponents);
+        self.seats = seats;
+        self
+    }
+
+    /// Chooses a play the same way as `choose_play`, but also explains why
+    pub fn choose_play_with_report(&self, game: &SafeGameInterface) -> (Play, DecisionReport) {
+        let (depth, first_plays) = self.search_first_plays(game, true, true);
+        let play = self.best_play(game, &first_plays);
+
+        let mut candidates: Vec<CandidateReport> = first_plays
+            .into_iter()
+            .map(|(play, first)| CandidateReport {
+                play,
+                leaves: first.leaves,
+                mean_cost: first.total_cost / first.leaves,
+                min_cost: first.min_cost,
+                best_continuation: first.best_continuation,
+                first_step: first.first_step,
+                branches: ALL_COST_CASES
+                    .iter()
+                    .zip(first.branches.iter())
+                    .filter(|(_, used)| used.uses > 0.0)
+                    .map(|(&case, &used)| BranchUse {
+                        case,
+                        uses: used.uses,
+                        total_cost: used.total_cost,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        candidates.sort_by_key(|c| OrderedFloat(c.mean_cost));
+
+        let report = DecisionReport {
+            chosen: play.clone(),
+            depth,
+            candidates,
+        };
+
+        (play, report)
+    }
+
+    /// The mean cost of every first play, worked out with the memo and again without it.
+    /// The two should be the same, the memo only saves time
+    pub fn compare_memo(&self, game: &SafeGameInterface) -> Vec<(Play, f64, f64)> {
+        let (_, memoized) = self.search_first_plays(game, false, true);
+        let (_, unmemoized) = self.search_first_plays(game, false, false);
+
+        let mean = |first: &FirstPlay| first.total_cost / first.leaves;
+        memoized
+            .iter()
+            .map(|(play, first)| {
+                let without = unmemoized.get(play).map_or(f64::NAN, mean);
+                (play.clone(), mean(first), without)
+            })
+            .collect()
+    }
+
+    fn search_first_plays(&self, game: &SafeGameInterface, trace: bool, memoize: bool) -> (usize, HashMap<Play, FirstPlay>) {
+        // iterative deepening: search one card deeper each time, and keep the results of the
+        // deepest search that finished before we ran out of time or nodes
         let hand = game.my_hand().to_vec();
         let n_cards = hand.len();
+        let hand_mask = card_mask(&hand);
         let available_plays = Finder::new(hand).all_plays();
 
-        // we can play any of these plays, or pass
-        let state = State::new(&self.constants, game);
-        let depth = min(6, n_cards);
+        let max_depth = min(self.budget.max_depth, n_cards);
+
+        // there is no point looking at fewer cards than the play we are trying to beat
+        let table_len = game.get_play_on_table().map_or(1, |p| p.cards().len());
+        let min_depth = min(table_len.max(1), max_depth);
+
+        let mut limits = SearchLimits::new(&self.budget);
+        let mut best = (0, HashMap::new());
 
-        let mut first_plays_with_average_cost: HashMap<Play, (f64, f64)> =
-            HashMap::with_capacity(available_plays.len());
+        // the situation is the one we are in now, for every play we plan
+        let situation = Situation::of(game);
+        let beaten_factor = self
+            .opponents
+            .as_ref()
+            .map_or(1.0, |o| o.response_factor(game, &self.seats));
+        let control: Vec<f64> = available_plays
+            .iter()
+            .map(|play| {
+                let beaten = 1.0 - self.control.probability(play, situation);
+                1.0 - (beaten * beaten_factor).min(1.0)
+            })
+            .collect();
+        let table_control = game
+            .get_play_on_table()
+            .map(|play| self.control.probability(play, situation));
+
+        // without any limits, the shallower searches would just be wasted work
+        let first_depth = if limits.is_unbounded() { max_depth } else { min_depth };
+
+        for depth in first_depth..=max_depth {
+            let mut search = Search::new(&available_plays, &control, &self.constants, &mut limits, trace);
+            search.memoize = memoize;
+
+            let mut first_plays: HashMap<Play, FirstPlay> =
+                HashMap::with_capacity(available_plays.len());
+
+            for (i, play) in available_plays.iter().enumerate() {
+                let n_cards = play.cards().len();
+                if depth < n_cards {
+                    continue;
+                }
+
+                let remaining = hand_mask & !search.masks[i];
+                let rest = search.continuations(remaining, depth - n_cards, i);
+                if rest.leaves == 0.0 {
+                    continue; // no way to spend exactly `depth` cards starting with this play
+                }
+
+                let first_step = first_step(game, play, table_control.unwrap_or(0.0));
+                let first_cost = first_step.value(&self.constants);
+
+                let entry = first_plays.entry(play.clone()).or_insert_with(|| FirstPlay {
+                    first_step,
+                    min_cost: f64::INFINITY,
+                    best_continuation: Vec::new(),
+                    ..FirstPlay::default()
+                });
+                entry.total_cost += rest.leaves * first_cost + rest.total_cost;
+                entry.leaves += rest.leaves;
+
+                if trace && first_cost + rest.min_cost < entry.min_cost {
+                    entry.min_cost = first_cost + rest.min_cost;
+                    entry.best_continuation = search.best_continuation(remaining, i);
+                }
 
-        search(depth, available_plays, state, &mut |state: State| {
-            // we want to
-            let first_play = state.our_plays_so_far.first().unwrap().clone();
+                if trace {
+                    for (total, used) in entry.branches.iter_mut().zip(rest.branches.iter()) {
+                        total.uses += used.uses;
+                        total.total_cost += used.total_cost;
+                    }
+                }
+            }
+
+            // the shallowest search always counts, so that we have something to play
+            if limits.exhausted && !best.1.is_empty() {
+                break;
+            }
 
-            let entry = first_plays_with_average_cost
-                .entry(first_play)
-                .or_insert((0.0, 0.0));
-            entry.0 += state.total_cost;
-            entry.1 += 1.0;
-        });
+            best = (depth, first_plays);
 
+            if limits.exhausted {
+                break;
+            }
+        }
+
+        best
+    }
+
+    fn best_play(&self, game: &SafeGameInterface, first_plays: &HashMap<Play, FirstPlay>) -> Play {
         // find the best one that doesn't involve passing
-        let best = first_plays_with_average_cost
+        let best = first_plays
             .iter()
-            .min_by_key(|(_play, (total, count))| OrderedFloat(total / count))
-            .unwrap()
-            .0
-            .cards()
-            .to_vec();
+            .min_by_key(|(_play, first)| OrderedFloat(first.total_cost / first.leaves))
+            .map(|(play, _)| play.cards().to_vec())
+            .unwrap_or_default();
 
         match game.can_play(best) {
             Ok(play) => play,
-            Err(_) => {
-                // we're gonna have to pass here
-                match game.can_play(vec![]) {
-                    Ok(pass) => pass,
-                    Err(e) => unreachable!("{:?}", e),
-                }
-            }
+            // nothing worth playing, or the budget ran out before the search found anything,
+            // so pass if we can and otherwise make the smallest play
+            Err(_) => runner::stand_in(game),
         }
     }
 }
 
-fn search(
-    card_depth: usize,
-    available_plays: Vec<Play>,
-    current_state: State,
-    f: &mut impl FnMut(State),
-) {
-    // f gets called on all of the leaf notes from our search
-    if card_depth == 0 {
-        f(current_state)
-    } else {
-        for play in available_plays.iter() {
-            let n_cards = play.cards().len();
-            if card_depth < n_cards {
-                continue;
-            }
+impl Player for MachinePlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        let (_depth, first_plays) = self.search_first_plays(game, false, true);
+        self.best_play(game, &first_plays)
+    }
+}
+
+// everything we found out about sequences that start with one particular play
+#[derive(Default)]
+struct FirstPlay {
+    first_step: FirstStep,
+    total_cost: f64,
+    leaves: f64,
+    // the rest are only filled in when we are tracing
+    min_cost: f64,
+    best_continuation: Vec<Play>,
+    branches: [BranchTotal; N_COST_CASES],
+}
 
-            // we want to construct a map with all of the plays available, excluding the ones that use the cards we just spent
-            let mut plays_available_to_child = available_plays.clone();
-            let cards_we_just_played: HashSet<&Card> = play.cards().iter().collect();
-            plays_available_to_child.retain(|p| {
-                // make sure that p doesn't overlap with any of the cards we just played
-                p.cards().iter().all(|c| !cards_we_just_played.contains(c))
-            });
+// keeps track of how much of the budget a search has used up
+struct SearchLimits {
+    deadline: Option<Instant>,
+    nodes_left: Option<usize>,
+    exhausted: bool,
+}
 
-            let child_state = current_state.next_state(play);
+impl SearchLimits {
+    fn new(budget: &SearchBudget) -> SearchLimits {
+        SearchLimits {
+            deadline: budget.time_limit.map(|t| Instant::now() + t),
+            nodes_left: budget.node_limit,
+            exhausted: false,
+        }
+    }
 
-            search(
-                card_depth - n_cards,
-                plays_available_to_child,
-                child_state,
-                f,
-            );
+    fn is_unbounded(&self) -> bool {
+        self.deadline.is_none() && self.nodes_left.is_none()
+    }
+
+    #[inline]
+    fn visit(&mut self) -> bool {
+        // call on every node, returns false once we should stop searching
+        if let Some(ref mut nodes_left) = self.nodes_left {
+            if *nodes_left == 0 {
+                self.exhausted = true;
+            } else {
+                *nodes_left -= 1;
+            }
         }
+
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                self.exhausted = true;
+            }
+        }
+
+        !self.exhausted
     }
 }
 
-// describes the state of the game after a move has been played
-#[derive(Clone)]
-struct State<'a> {
-    // this is the play that on our turn, we are looking to play on top of.
-    // None if it is the first turn
+// we look at every sequence of our own plays that spends exactly `card_depth` cards,
+// and average the cost of the sequences by the play they start with.
+// the cost of a sequence only depends on which plays are next to each other, so the sum over
+// every way of finishing a sequence only depends on the cards we have left and the last play we made.
+// we memoize on that, so that reaching the same cards through a different order of plays is free
+struct Search<'a> {
+    plays: &'a [Play],
+    control: &'a [f64], // how likely each play is to win its trick
+    masks: Vec<u64>,
     constants: &'a [f64; N_PARAMETERS],
-    status: Status,
+    memo: HashMap<(u64, usize), Continuations>,
+    memoize: bool, // only turned off to check that the memo doesn't change the answer
+    limits: &'a mut SearchLimits,
+    trace: bool,
+}
+
+// totals over every way of finishing a sequence
+#[derive(Clone, Copy)]
+struct Continuations {
+    leaves: f64,
     total_cost: f64,
-    our_plays_so_far: Vec<Play>,
-    game_interface: &'a SafeGameInterface<'a>,
+    // the rest are only filled in when we are tracing
+    min_cost: f64,
+    best_next: Option<usize>,
+    branches: [BranchTotal; N_COST_CASES],
 }
 
-#[derive(Clone)]
-enum Status {
-    FirstTurnOfGame,
-    FirstAnalysis(Play), // previous term
-    Rest(Play),          // four terms before
+#[derive(Clone, Copy, Default)]
+struct BranchTotal {
+    uses: f64,
+    total_cost: f64,
+}
+
+impl Continuations {
+    fn none() -> Continuations {
+        Continuations {
+            leaves: 0.0,
+            total_cost: 0.0,
+            min_cost: f64::INFINITY,
+            best_next: None,
+            branches: Default::default(),
+        }
+    }
+
+    fn leaf() -> Continuations {
+        Continuations {
+            leaves: 1.0,
+            min_cost: 0.0,
+            ..Continuations::none()
+        }
+    }
 }
 
-impl<'a> State<'a> {
+impl<'a> Search<'a> {
     fn new(
+        plays: &'a [Play],
+        control: &'a [f64],
         constants: &'a [f64; N_PARAMETERS],
-        game_interface: &'a SafeGameInterface<'a>,
-    ) -> State<'a> {
-        let status = match game_interface.get_play_on_table() {
-            Some(play) => Status::FirstAnalysis(play.clone()),
-            None => Status::FirstTurnOfGame,
-        };
-
-        State {
+        limits: &'a mut SearchLimits,
+        trace: bool,
+    ) -> Search<'a> {
+        Search {
+            plays,
+            control,
+            masks: plays.iter().map(|p| card_mask(p.cards())).collect(),
             constants,
-            status,
-            total_cost: 0.0,
-            our_plays_so_far: vec![],
-            game_interface,
+            memo: HashMap::new(),
+            memoize: true,
+            limits,
+            trace,
         }
     }
 
-    #[inline]
-    fn next_state(&self, play: &Play) -> State {
-        let mut new_state = self.clone();
+    fn continuations(&mut self, remaining: u64, card_depth: usize, previous: usize) -> Continuations {
+        if card_depth == 0 {
+            return Continuations::leaf();
+        }
 
-        new_state.total_cost += match new_state.status {
-            Status::FirstTurnOfGame => {
-                if play.cards().contains(&THREE_OF_CLUBS) {
-                    0.0 // we literally won't be able to pass
-                } else {
-                    INFINITY
-                }
+        if self.memoize {
+            if let Some(&known) = self.memo.get(&(remaining, previous)) {
+                return known;
             }
-            Status::FirstAnalysis(ref before) => {
-                // we are trying to play directly on these cards
-                if new_state
-                    .game_interface
-                    .can_play(play.cards().to_vec())
-                    .is_ok()
-                {
-                    0.0
-                } else {
-                    // how many turns do we think it will take
-                    // TODO: include numbers from research!
-                    first_analysis_cost(before, play, self.constants)
-                }
+        }
+
+        if !self.limits.visit() {
+            return Continuations::none();
+        }
+
+        let mut result = Continuations::none();
+
+        for next in 0..self.plays.len() {
+            let n_cards = self.plays[next].cards().len();
+
+            // make sure that we still have all of the cards for this play
+            if card_depth < n_cards || self.masks[next] & !remaining != 0 {
+                continue;
             }
-            Status::Rest(ref four_turns_before) => {
-                // TODO: include numbers from the research!
-                cost(&four_turns_before, play, self.constants)
+
+            let rest = self.continuations(remaining & !self.masks[next], card_depth - n_cards, next);
+            if rest.leaves == 0.0 {
+                continue;
             }
-        };
 
-        // change the status going forward
-        new_state.status = Status::Rest(play.clone());
+            let (before, play) = (&self.plays[previous], &self.plays[next]);
+            let control = self.control[previous];
+            let case = cost_case(before, play, control >= 0.5);
+            let step = expected_cost(before, play, control, self.constants, 0);
 
-        new_state.our_plays_so_far.push(play.clone());
-        new_state
+            result.leaves += rest.leaves;
+            result.total_cost += rest.leaves * step + rest.total_cost;
+
+            if self.trace {
+                if step + rest.min_cost < result.min_cost || result.best_next.is_none() {
+                    result.min_cost = step + rest.min_cost;
+                    result.best_next = Some(next);
+                }
+
+                for (total, used) in result.branches.iter_mut().zip(rest.branches.iter()) {
+                    total.uses += used.uses;
+                    total.total_cost += used.total_cost;
+                }
+
+                let this_branch = &mut result.branches[case as usize];
+                this_branch.uses += rest.leaves;
+                this_branch.total_cost += rest.leaves * step;
+            }
+        }
+
+        self.memo.insert((remaining, previous), result);
+        result
+    }
+
+    fn best_continuation(&self, mut remaining: u64, mut previous: usize) -> Vec<Play> {
+        // follow the cheapest choices back out of the memo
+        let mut sequence = Vec::new();
+
+        while let Some(next) = self.memo.get(&(remaining, previous)).and_then(|c| c.best_next) {
+            sequence.push(self.plays[next].clone());
+            remaining &= !self.masks[next];
+            previous = next;
+        }
+
+        sequence
     }
 }
 
-fn first_analysis_cost(play1: &Play, play2: &Play, constants: &[f64; N_PARAMETERS]) -> f64 {
-    assert!(!play1.is_pass() && !play2.is_pass());
+/// Which rule priced the first play of a sequence
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub enum FirstStep {
+    ThreeOfClubs,        // first turn of the game, and we play the 3♣
+    MissingThreeOfClubs, // first turn of the game, and we don't
+    #[default]
+    PlaysOnTable, // we can play this right now
+    // the price if the play on the table wins its trick and if it doesn't, blended by how likely it is to
+    FirstAnalysis {
+        control: f64,
+        won: CostCase,
+        beaten: CostCase,
+    },
+}
 
-    let play1_rank = play1.ranking_card().unwrap();
-    let play2_rank = play2.ranking_card().unwrap();
+impl FirstStep {
+    fn value(self, constants: &[f64]) -> f64 {
+        match self {
+            FirstStep::ThreeOfClubs => 0.0, // we literally won't be able to pass
+            FirstStep::MissingThreeOfClubs => f64::INFINITY,
+            FirstStep::PlaysOnTable => 0.0,
+            FirstStep::FirstAnalysis {
+                control,
+                won,
+                beaten,
+            } => control * won.value(constants, 5) + (1.0 - control) * beaten.value(constants, 5),
+        }
+    }
+}
 
-    match play2.kind() {
-        PlayKind::Pass => unimplemented!(),
+impl fmt::Display for FirstStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FirstStep::ThreeOfClubs => f.write_str("three-of-clubs"),
+            FirstStep::MissingThreeOfClubs => f.write_str("missing-three-of-clubs"),
+            FirstStep::PlaysOnTable => f.write_str("plays-on-table"),
+            FirstStep::FirstAnalysis { won, beaten, .. } if won == beaten => {
+                write!(f, "first-analysis/{}", won)
+            }
+            FirstStep::FirstAnalysis {
+                control,
+                won,
+                beaten,
+            } => write!(
+                f,
+                "first-analysis/{} {:.0}%, {} {:.0}%",
+                won,
+                100.0 * control,
+                beaten,
+                100.0 * (1.0 - control)
+            ),
+        }
+    }
+}
 
-        PlayKind::Single | PlayKind::Pair => {
-            // how much higher is play2 than play1
-            if would_get_control(play1) {
-                constants[5]
+fn first_step(game: &SafeGameInterface, play: &Play, table_control: f64) -> FirstStep {
+    match game.get_play_on_table() {
+        // a clean table after the first turn only comes from setting up a position, and then anything goes
+        None if !game.is_first_turn() => FirstStep::PlaysOnTable,
+        None => {
+            if play.cards().contains(&THREE_OF_CLUBS) {
+                FirstStep::ThreeOfClubs
             } else {
-                // we'd better hope that it goes around and doesn't get higher than play2
-                let gap = play2_rank.numeric_value() as isize - play1_rank.numeric_value() as isize;
-
-                if gap > 20 {
-                    // i guess we can say we are pretty good
-                    constants[6]
-                } else {
-                    // just a big number, we will probably hand control over to someone else
-                    // and hope that eventually we will get control bac
-                    constants[7]
+                FirstStep::MissingThreeOfClubs
+            }
+        }
+        Some(before) => {
+            // we are trying to play directly on these cards
+            if game.can_play(play.cards().to_vec()).is_ok() {
+                FirstStep::PlaysOnTable
+            } else {
+                // how many turns do we think it will take
+                FirstStep::FirstAnalysis {
+                    control: table_control,
+                    won: cost_case(before, play, true),
+                    beaten: cost_case(before, play, false),
                 }
             }
         }
-        PlayKind::Strait | PlayKind::Flush => {
-            // this is very dependant on what cards the other players have
+    }
+}
 
-            constants[8]
+/// Which branch of `cost` a pair of plays falls into
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum CostCase {
+    AfterControl,
+    WideGap,
+    NarrowGap,
+    StraitOrFlush,
+    FullHouse,
+    Bomb,
+}
+
+const N_COST_CASES: usize = 6;
+
+pub const ALL_COST_CASES: [CostCase; N_COST_CASES] = [
+    CostCase::AfterControl,
+    CostCase::WideGap,
+    CostCase::NarrowGap,
+    CostCase::StraitOrFlush,
+    CostCase::FullHouse,
+    CostCase::Bomb,
+];
+
+impl CostCase {
+    fn value(self, constants: &[f64], offset: usize) -> f64 {
+        // `cost` uses the first five constants, and the first analysis (of the play on the table)
+        // uses the next five
+        match self {
+            CostCase::AfterControl => constants[offset],
+            CostCase::WideGap => constants[offset + 1],
+            CostCase::NarrowGap => constants[offset + 2],
+            CostCase::StraitOrFlush => constants[offset + 3],
+            CostCase::FullHouse => constants[offset + 4],
+            CostCase::Bomb => 0.0,
         }
-        PlayKind::FullHouse => constants[9],
-        PlayKind::FourOfAKind => 0.0, // so rare, doesn't even matter
-        PlayKind::StraitFlush => 0.0,
+    }
+}
+
+impl fmt::Display for CostCase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CostCase::AfterControl => "after-control",
+            CostCase::WideGap => "wide-gap",
+            CostCase::NarrowGap => "narrow-gap",
+            CostCase::StraitOrFlush => "strait-or-flush",
+            CostCase::FullHouse => "full-house",
+            CostCase::Bomb => "bomb",
+        })
     }
 }
 
@@ -278,8 +651,19 @@ pub fn cost(play1: &Play, play2: &Play, constants: &[f64]) -> f64 {
 
     // right now, we are just going to use a 'heuristic' approach
 
-    // TODO: use numbers from research
+    // how likely play1 is to get control comes from `control`, for a play at the start of a game
+
+    let control = ControlTable::built_in().probability(play1, Situation::default());
+    expected_cost(play1, play2, control, constants, 0)
+}
+
+fn expected_cost(play1: &Play, play2: &Play, control: f64, constants: &[f64], offset: usize) -> f64 {
+    // `control` is how likely play1 is to win its trick, which decides which branch we are in
+    control * cost_case(play1, play2, true).value(constants, offset)
+        + (1.0 - control) * cost_case(play1, play2, false).value(constants, offset)
+}
 
+fn cost_case(play1: &Play, play2: &Play, gets_control: bool) -> CostCase {
     assert!(!play1.is_pass() && !play2.is_pass());
 
     let play1_rank = play1.ranking_card().unwrap();
@@ -290,50 +674,29 @@ pub fn cost(play1: &Play, play2: &Play, constants: &[f64]) -> f64 {
 
         PlayKind::Single | PlayKind::Pair => {
             // how much higher is play2 than play1
-            if would_get_control(play1) {
-                constants[0]
+            if gets_control {
+                CostCase::AfterControl
             } else {
                 // we'd better hope that it goes around and doesn't get higher than play2
                 let gap = play2_rank.numeric_value() as isize - play1_rank.numeric_value() as isize;
 
                 if gap > 20 {
                     // i guess we can say we are pretty good
-                    constants[1]
+                    CostCase::WideGap
                 } else {
                     // just a big number, we will probably hand control over to someone else
                     // and hope that eventually we will get control bac
-                    constants[2]
+                    CostCase::NarrowGap
                 }
             }
         }
         PlayKind::Strait | PlayKind::Flush => {
             // this is very dependant on what cards the other players have
 
-            constants[3]
-        }
-        PlayKind::FullHouse => constants[4],
-        PlayKind::FourOfAKind => 0.0, // so rare, doesn't even matter
-        PlayKind::StraitFlush => 0.0,
-    }
-}
-
-fn would_get_control(play: &Play) -> bool {
-    // figure out how high this play is relative to other plays
-
-    // this is a terrible implementation
-
-    // TODO: use data!
-    match play.kind() {
-        PlayKind::Pass => unimplemented!(),
-        PlayKind::Single | PlayKind::Pair => {
-            let rank = play.ranking_card().unwrap().rank;
-
-            rank == Rank::Two || rank == Rank::Ace
+            CostCase::StraitOrFlush
         }
-        PlayKind::Strait => false,
-        PlayKind::Flush => false,
-        PlayKind::FullHouse => true, // this is dummy stuff
-        PlayKind::FourOfAKind => true,
-        PlayKind::StraitFlush => true,
+        PlayKind::FullHouse => CostCase::FullHouse,
+        PlayKind::FourOfAKind => CostCase::Bomb, // so rare, doesn't even matter
+        PlayKind::StraitFlush => CostCase::Bomb,
     }
 }
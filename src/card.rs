@@ -29,6 +29,20 @@ pub fn entire_deck() -> Vec<Card> {
     cards
 }
 
+pub fn card_mask(cards: &[Card]) -> u64 {
+    // one bit per card, by numeric_value
+    cards
+        .iter()
+        .fold(0, |mask, card| mask | 1 << card.numeric_value())
+}
+
+pub fn parse_cards(s: &str) -> Result<Vec<Card>, String> {
+    // cards separated by whitespace, like "3C 4♦ TS"
+    s.split_whitespace()
+        .map(|c| c.parse().map_err(|()| format!("not a card: {}", c)))
+        .collect()
+}
+
 // note: ord impl compares rank first, then suit
 // this is how cards are ranked in pusoy
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Deserialize, Serialize)]
@@ -60,11 +74,14 @@ impl FromStr for Card {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Card, ()> {
-        let first_char = s.chars().nth(0).unwrap().to_string();
-        let second_char = s.chars().nth(1).unwrap().to_string();
+        let mut chars = s.trim().chars();
+
+        let rank = chars.next().ok_or(())?.to_string().parse()?;
+        let suit = chars.next().ok_or(())?.to_string().parse()?;
 
-        let rank = first_char.parse()?;
-        let suit = second_char.parse()?;
+        if chars.next().is_some() {
+            return Err(());
+        }
 
         Ok(Card { rank, suit })
     }
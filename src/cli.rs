@@ -0,0 +1,1333 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng, SeedableRng};
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::iter;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::analysis::{self, AnalysisReport, Evaluator};
+use crate::bot::{HumanPlayer, MachinePlayer, Player, SearchBudget, N_PARAMETERS};
+use crate::card::{entire_deck, parse_cards, Card, THREE_OF_CLUBS};
+use crate::client::{self, ClientConfig};
+use crate::control;
+use crate::engine;
+use crate::events::{GameEvent, Observer};
+use crate::export;
+use crate::game::{GameRecord, GameState, Position, Rules, SafeGameInterface};
+use crate::neural::{self, Network, Optimizer};
+use crate::opponent::Opponents;
+use crate::partition::{self, Objective};
+use crate::play::finder::Finder;
+use crate::play::Play;
+use crate::protocol;
+use crate::registry::{
+    self, make_player, make_seated_player, read_network, read_opponents, read_parameters,
+};
+use crate::reinforce::{self, ReinforceConfig};
+use crate::review::{self, ReviewConfig};
+use crate::runner::{run_game, Match};
+use crate::server::{self, BotFactory, SeatConfig, ServerConfig, TableConfig};
+use crate::stats::Stats;
+use crate::strength;
+use crate::threat::{self, Threats};
+use crate::train::{save_parameters, training_step, DEFAULT_PARAMETERS};
+use crate::tui::TerminalPlayer;
+
+const USAGE: &str = "usage: pusoy <command> [options]
+
+commands:
+    play        play against bots from the terminal (--tui for the full screen version).
+                --model FILE keeps learning how everyone plays (you as --name), for machine:opponents=FILE.
+                --assist suggests plays and warns before plays that break up your hand
+    selfplay    watch bots play each other (--events FILE saves what happened as json lines)
+    train       improve the bots (--mode abc, neural or reinforce), or --mode control to count how
+                often each play wins its trick over --games N, for machine:control=FILE,
+                or --mode strength to fit how hand strength turns into wins
+    tournament  rank several bots against each other (--baselines to also play each of them
+                against random-legal, greedy-lowest and pass-avoiding)
+    replay      step through a saved game: replay <file>
+    export      write a saved game out as a page that plays it back: export <file> [--out FILE]
+    analyze     rank every play from a position: analyze --hand \"3C 4D ...\" [--table \"9S\"]
+                (--owner N played the table, --sizes or --opponents \"..|..\" for the other hands,
+                --evaluator heuristic, mcts or exact, --iterations, --time-ms, --depth,
+                --json, --explain for why the heuristic bot makes its play)
+    stats       numbers about how games go: stats [saved games or directories of them] [--events FILE],
+                or with neither, from --games N (default 100) new games between --bots.
+                --format csv or json, --out FILE
+    review      find the blunders one seat made in a saved game: review <file> --seat N
+                (--threshold in win probability, default 0.1, --iterations, --html FILE, --json)
+    threats     how likely someone can beat a play: threats --hand \"3C 4D ...\" [--play \"9H 9S\"]
+                (--played for cards already gone, --sizes for the other hands, --samples N, --json).
+                without --play, the best play of every kind in the hand
+    partition   the best ways to split a hand into plays: partition --hand \"3C 4D ...\" [--top K]
+                (--objective fewest-plays or fewest-low-singles, --json)
+    strength    how good a freshly dealt hand is: strength --hand \"3C 4D ...\" [--json]
+    bench       time how long the bots take to decide (--check-search to make sure the machine
+                bot's memoized search agrees with searching everything)
+    serve       host tables for playing over the network (--remote N seats, the rest are --bots),
+                and a browser client on --web (default 127.0.0.1:8080, \"off\" to turn it off)
+    connect     sit down at a table on a server (--address, --table, --seat, --token, --auto,
+                --assist for hints and warnings)
+    engine      speak the engine protocol on stdin and stdout, playing as --bots (default machine)
+    bots        list the bots that --bots can name, and the difficulty levels
+
+common options:
+    --players N       how many players (default 4)
+    --play-to-end     keep playing until everyone has a place
+    --seed N          seed for shuffling the deck
+    --bots SPECS      space separated bot specs, like \"machine:depth=4,time_ms=50 neural:weights=net.json\"
+                      (engine:path=<program>,timeout_ms=<ms> plays with an outside program,
+                      any bot with mistakes=0.2 makes some other play a fifth of the time)
+    --params FILE     parameters for `machine` bots that don't name their own";
+
+// options that don't take a value
+const FLAGS: [&str; 9] = [
+    "--play-to-end",
+    "--json",
+    "--reveal",
+    "--tui",
+    "--auto",
+    "--explain",
+    "--assist",
+    "--baselines",
+    "--check-search",
+];
+
+pub fn run(args: Vec<String>) -> Result<(), String> {
+    let command = match args.get(1) {
+        Some(command) => command.as_str(),
+        None => return Err(USAGE.to_string()),
+    };
+
+    let args = Args::parse(&args[2..])?;
+
+    match command {
+        "play" => play(&args),
+        "selfplay" => selfplay(&args),
+        "train" => train(&args),
+        "tournament" => tournament(&args),
+        "replay" => replay(&args),
+        "export" => export(&args),
+        "analyze" => analyze(&args),
+        "stats" => stats(&args),
+        "review" => review(&args),
+        "threats" => threats(&args),
+        "strength" => hand_strength(&args),
+        "partition" => partition(&args),
+        "bench" => bench(&args),
+        "serve" => serve(&args),
+        "connect" => connect(&args),
+        "engine" => run_engine(&args),
+        "bots" => {
+            list_bots();
+            Ok(())
+        }
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(format!("unknown command: {}\n\n{}", command, USAGE)),
+    }
+}
+
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: HashSet<String>,
+}
+
+impl Args {
+    fn parse(raw: &[String]) -> Result<Args, String> {
+        let mut args = Args {
+            positional: Vec::new(),
+            options: HashMap::new(),
+            flags: HashSet::new(),
+        };
+
+        let mut raw = raw.iter();
+
+        while let Some(arg) = raw.next() {
+            if !arg.starts_with("--") {
+                args.positional.push(arg.clone());
+            } else if FLAGS.contains(&arg.as_str()) {
+                args.flags.insert(arg.clone());
+            } else if let Some(equals) = arg.find('=') {
+                args.options
+                    .insert(arg[..equals].to_string(), arg[equals + 1..].to_string());
+            } else {
+                let value = raw.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                args.options.insert(arg.clone(), value.clone());
+            }
+        }
+
+        Ok(args)
+    }
+
+    fn get<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        match self.options.get(name) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("bad value for {}: {}", name, value)),
+            None => Ok(default),
+        }
+    }
+
+    fn opt(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(|s| s.as_str())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    fn rules(&self) -> Result<Rules, String> {
+        let rules = Rules {
+            n_players: self.get("--players", 4)?,
+            play_to_end: self.flag("--play-to-end"),
+        };
+        rules.check()?;
+        Ok(rules)
+    }
+
+    fn seed(&self) -> Result<u64, String> {
+        match self.opt("--seed") {
+            Some(_) => self.get("--seed", 0),
+            None => Ok(thread_rng().gen()),
+        }
+    }
+
+    fn budget(&self) -> Result<SearchBudget, String> {
+        let default = SearchBudget::default();
+        Ok(SearchBudget {
+            max_depth: self.get("--depth", default.max_depth)?,
+            time_limit: match self.opt("--time-ms") {
+                Some(_) => Some(Duration::from_millis(self.get("--time-ms", 0)?)),
+                None => None,
+            },
+            node_limit: match self.opt("--iterations") {
+                Some(_) => Some(self.get("--iterations", 0)?),
+                None => None,
+            },
+        })
+    }
+
+    fn parameters(&self) -> Result<[f64; N_PARAMETERS], String> {
+        match self.opt("--params") {
+            Some(path) => read_parameters(path),
+            None => Ok(DEFAULT_PARAMETERS),
+        }
+    }
+
+    fn bots(&self, default: &str) -> Vec<String> {
+        self.opt("--bots")
+            .unwrap_or(default)
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect()
+    }
+}
+
+fn make_players(
+    specs: &[String],
+    n_players: usize,
+    parameters: [f64; N_PARAMETERS],
+) -> Result<Vec<Box<dyn Player>>, String> {
+    make_seated_players(specs, n_players, parameters, &[])
+}
+
+// the same, for a table where `seats` says who is sitting where
+fn make_seated_players(
+    specs: &[String],
+    n_players: usize,
+    parameters: [f64; N_PARAMETERS],
+    seats: &[String],
+) -> Result<Vec<Box<dyn Player>>, String> {
+    // a single spec fills every seat
+    match specs.len() {
+        1 => (0..n_players)
+            .map(|_| make_seated_player(&specs[0], parameters, seats))
+            .collect(),
+        n if n == n_players => specs
+            .iter()
+            .map(|s| make_seated_player(s, parameters, seats))
+            .collect(),
+        n => Err(format!("{} bots given for {} seats", n, n_players)),
+    }
+}
+
+fn list_bots() {
+    for (name, description) in registry::BOTS.iter() {
+        println!("{:<14} {}", name, description);
+    }
+    println!();
+    for (level, spec) in registry::LEVELS.iter() {
+        println!("{:<14} is {}", level, spec);
+    }
+}
+
+fn shuffled_deck(seed: u64) -> Vec<Card> {
+    let mut deck = entire_deck();
+    deck.shuffle(&mut StdRng::seed_from_u64(seed));
+    deck
+}
+
+fn describe(interface: &SafeGameInterface, play: &Play) -> String {
+    if play.is_pass() {
+        format!("seat {} passes", interface.my_seat())
+    } else {
+        format!("seat {} plays {}", interface.my_seat(), play)
+    }
+}
+
+fn describe_event(event: &GameEvent) -> Option<String> {
+    Some(match *event {
+        GameEvent::Dealt { first, .. } => format!("seat {} has the 3♣", first),
+        GameEvent::Played { seat, ref play } => format!("seat {} plays {}", seat, play),
+        GameEvent::Passed { seat } => format!("seat {} passes", seat),
+        GameEvent::TrickCleared => return None,
+        GameEvent::ControlGained { seat } => format!("seat {} has control", seat),
+        GameEvent::PlayerFinished { seat, place } => {
+            format!("seat {} is out, in place {}", seat, place + 1)
+        }
+        GameEvent::Forfeited { seat, ref reason } => format!("seat {} forfeits: {}", seat, reason),
+        GameEvent::GameOver { .. } => "game over".to_string(),
+    })
+}
+
+fn print_placements(game: &GameState) {
+    for (seat, place) in game.placements().iter().enumerate() {
+        println!(
+            "seat {}: place {} ({} cards left)",
+            seat,
+            place + 1,
+            game.hands[seat].len()
+        );
+    }
+}
+
+fn save_record(game: &GameState, path: &Path) -> Result<(), String> {
+    game.to_record()
+        .save(path)
+        .map_err(|e| format!("couldn't save game to {}: {}", path.display(), e))
+}
+
+fn play(args: &Args) -> Result<(), String> {
+    let rules = args.rules()?;
+    let seed = args.seed()?;
+    let seat: usize = args.get("--seat", 0)?;
+
+    if seat >= rules.n_players {
+        return Err(format!("there is no seat {}", seat));
+    }
+
+    // who is sitting where, for learning how everyone plays and for the bots that use what was learned
+    let specs = args.bots("machine");
+    let mut names: Vec<String> = specs
+        .iter()
+        .cycle()
+        .take(rules.n_players - 1)
+        .cloned()
+        .collect();
+    names.insert(seat, args.opt("--name").unwrap_or("you").to_string());
+
+    let bots = make_seated_players(&specs, rules.n_players - 1, args.parameters()?, &names)?;
+
+    // the terminal ui keeps its own log, so we only print the plays for the plain version
+    let tui = args.flag("--tui");
+    if tui && !io::stdin().is_terminal() {
+        return Err("--tui needs to be run in a terminal".to_string());
+    }
+
+    let mut players: Vec<Box<dyn Player>> = bots;
+    if tui {
+        players.insert(
+            seat,
+            Box::new(TerminalPlayer {
+                assist: args.flag("--assist"),
+            }),
+        );
+    } else {
+        players.insert(seat, Box::new(HumanPlayer));
+    }
+
+    println!("you are seat {} (seed {})", seat, seed);
+
+    let mut print_events = |event: &GameEvent| {
+        if let (false, Some(text)) = (tui, describe_event(event)) {
+            println!("{}", text);
+        }
+    };
+
+    // learn how everyone plays, adding to what we learned in earlier games
+    let model_path = args.opt("--model");
+    let mut opponents = match model_path {
+        Some(path) if Path::new(path).exists() => read_opponents(path)?,
+        _ => Opponents::new(),
+    };
+    opponents.sit(names);
+
+    let game = run_game(
+        &players,
+        GameState::with_rules(rules, shuffled_deck(seed)),
+        &mut [&mut print_events, &mut opponents],
+    );
+
+    print_placements(&game);
+
+    if let Some(path) = model_path {
+        opponents
+            .save(path)
+            .map_err(|e| format!("couldn't save opponent models to {}: {}", path, e))?;
+    }
+
+    if let Some(path) = args.opt("--save") {
+        save_record(&game, Path::new(path))?;
+    }
+
+    Ok(())
+}
+
+fn selfplay(args: &Args) -> Result<(), String> {
+    let rules = args.rules()?;
+    let seed = args.seed()?;
+    let n_games: u64 = args.get("--games", 1)?;
+    let specs = args.bots("machine");
+    let save_dir = args.opt("--save-dir").map(PathBuf::from);
+
+    if let Some(ref dir) = save_dir {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+
+    // every event of every game, as json lines
+    let mut event_log = match args.opt("--events") {
+        Some(path) => Some(BufWriter::new(
+            File::create(path).map_err(|e| format!("couldn't create {}: {}", path, e))?,
+        )),
+        None => None,
+    };
+
+    for g in 0..n_games {
+        let players = make_players(&specs, rules.n_players, args.parameters()?)?;
+
+        let mut log_error = None;
+        let mut observer = |event: &GameEvent| {
+            if n_games == 1 {
+                if let Some(text) = describe_event(event) {
+                    println!("{}", text);
+                }
+            }
+            if let Some(ref mut log) = event_log {
+                let written = serde_json::to_writer(&mut *log, event)
+                    .map_err(io::Error::from)
+                    .and_then(|()| writeln!(log));
+                if let Err(e) = written {
+                    log_error = Some(e);
+                }
+            }
+        };
+
+        let game = run_game(
+            &players,
+            GameState::with_rules(rules, shuffled_deck(seed.wrapping_add(g))),
+            &mut [&mut observer],
+        );
+
+        if let Some(e) = log_error {
+            return Err(format!("couldn't write events: {}", e));
+        }
+
+        println!(
+            "game {} (seed {}): {} plays, placements {:?}",
+            g,
+            seed.wrapping_add(g),
+            game.get_record().len(),
+            game.placements()
+        );
+
+        if let Some(ref dir) = save_dir {
+            save_record(
+                &game,
+                &dir.join(format!("game_{}.json", seed.wrapping_add(g))),
+            )?;
+        }
+    }
+
+    if let Some(mut log) = event_log {
+        log.flush()
+            .map_err(|e| format!("couldn't write events: {}", e))?;
+    }
+
+    Ok(())
+}
+
+fn train(args: &Args) -> Result<(), String> {
+    match args.opt("--mode").unwrap_or("abc") {
+        "abc" => train_abc(args),
+        "neural" => train_neural(args),
+        "reinforce" => train_reinforce(args),
+        "control" => train_control(args),
+        "strength" => train_strength(args),
+        mode => Err(format!("unknown training mode: {}", mode)),
+    }
+}
+
+fn train_abc(args: &Args) -> Result<(), String> {
+    // the original loop: keep improving the parameters, forever unless told otherwise
+    let steps: usize = args.get("--steps", 0)?;
+    let out = args.opt("--out");
+    let mut current_parameters = args.parameters()?;
+
+    for step in 0.. {
+        if steps != 0 && step >= steps {
+            break;
+        }
+
+        let results = training_step(current_parameters);
+
+        println!("{:?}", results);
+
+        current_parameters = results.0;
+
+        if let Some(path) = out {
+            save_parameters(&current_parameters, path).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn train_control(args: &Args) -> Result<(), String> {
+    // watch --bots play each other, and count how often each play wins its trick
+    let rules = args.rules()?;
+    let seed = args.seed()?;
+    let n_games = args.get("--games", 2000)?;
+    let out = args.opt("--out").unwrap_or("control.json");
+    let specs = args.bots("machine");
+    let parameters = args.parameters()?;
+
+    // make sure the specs work before going off in parallel
+    make_players(&specs, rules.n_players, parameters)?;
+
+    let table = control::build_table(rules, n_games, seed, || {
+        make_players(&specs, rules.n_players, parameters).unwrap()
+    });
+
+    table
+        .save(out)
+        .map_err(|e| format!("couldn't save the table to {}: {}", out, e))?;
+    println!("watched {} games, saved to {}", table.games(), out);
+
+    Ok(())
+}
+
+fn train_strength(args: &Args) -> Result<(), String> {
+    // fit how hand strength turns into the chance to go out first, from games between --bots
+    let rules = args.rules()?;
+    let seed = args.seed()?;
+    let n_games = args.get("--games", 2000)?;
+    let specs = args.bots("machine");
+    let parameters = args.parameters()?;
+
+    make_players(&specs, rules.n_players, parameters)?;
+
+    let model = strength::fit_win_model(rules, n_games, seed, || {
+        make_players(&specs, rules.n_players, parameters).unwrap()
+    })?;
+    println!(
+        "fitted to {} games between {} (seed {}), for WIN_MODEL in src/strength.rs:",
+        n_games,
+        specs.join(" "),
+        seed
+    );
+    println!("{:?}", model);
+
+    Ok(())
+}
+
+fn train_neural(args: &Args) -> Result<(), String> {
+    let rules = args.rules()?;
+    let seed = args.seed()?;
+    let n_games = args.get("--games", 100)?;
+    let epochs = args.get("--epochs", 20)?;
+    let batch_size = args.get("--batch", 32)?;
+    let learning_rate = args.get("--lr", 1e-3)?;
+    let out = args.opt("--out").unwrap_or("network.json");
+
+    let hidden: Vec<usize> = args
+        .opt("--hidden")
+        .unwrap_or("32,32")
+        .split(',')
+        .map(|h| h.parse().map_err(|_| format!("bad layer size: {}", h)))
+        .collect::<Result<_, _>>()?;
+
+    let mut optimizer = match args.opt("--optimizer").unwrap_or("adam") {
+        "adam" => Optimizer::adam(learning_rate),
+        "sgd" => Optimizer::sgd(learning_rate),
+        other => return Err(format!("unknown optimizer: {}", other)),
+    };
+
+    // the teachers, checked here so that we don't fail inside of the worker threads
+    let specs = args.bots("machine");
+    let parameters = args.parameters()?;
+    make_players(&specs, rules.n_players, parameters)?;
+
+    println!("playing {} games", n_games);
+    let dataset = neural::self_play_dataset(n_games, || {
+        make_players(&specs, rules.n_players, parameters).unwrap()
+    });
+
+    println!("training on {} decisions", dataset.len());
+    let mut network = match args.opt("--weights") {
+        Some(path) => read_network(path)?,
+        None => Network::new(&hidden, seed),
+    };
+
+    let losses = neural::train(
+        &mut network,
+        &dataset,
+        &mut optimizer,
+        epochs,
+        batch_size,
+        seed,
+    );
+    for (epoch, loss) in losses.iter().enumerate() {
+        println!("epoch {}: loss {:.4}", epoch, loss);
+    }
+
+    network.save(out).map_err(|e| e.to_string())
+}
+
+fn train_reinforce(args: &Args) -> Result<(), String> {
+    let default = ReinforceConfig::default();
+
+    let config = ReinforceConfig {
+        iterations: args.get("--iterations", default.iterations)?,
+        games_per_iteration: args.get("--games", default.games_per_iteration)?,
+        learning_rate: args.get("--lr", default.learning_rate)?,
+        temperature: args.get("--temperature", default.temperature)?,
+        eval_every: args.get("--eval-every", default.eval_every)?,
+        eval_games: args.get("--eval-games", default.eval_games)?,
+        checkpoint_every: args.get("--checkpoint-every", default.checkpoint_every)?,
+        checkpoint_dir: args.get("--checkpoint-dir", default.checkpoint_dir)?,
+        log_path: args.get("--log", default.log_path)?,
+        seed: args.seed()?,
+        ..default
+    };
+
+    let mut network = match args.opt("--weights") {
+        Some(path) => read_network(path)?,
+        None => Network::new(&[32, 32], config.seed),
+    };
+
+    reinforce::reinforce(&mut network, &config).map_err(|e| e.to_string())?;
+
+    network
+        .save(args.opt("--out").unwrap_or("network.json"))
+        .map_err(|e| e.to_string())
+}
+
+fn tournament(args: &Args) -> Result<(), String> {
+    let specs = args.bots("");
+    if specs.len() < 2 {
+        return Err("a tournament needs at least two --bots".to_string());
+    }
+
+    let rules = Rules {
+        n_players: specs.len(),
+        play_to_end: args.flag("--play-to-end"),
+    };
+    rules.check()?;
+
+    let seed = args.seed()?;
+    let n_games: usize = args.get("--games", 20)?;
+    let parameters = args.parameters()?;
+
+    let (wins, total_placement) = rotate_seats(&specs, rules, seed, n_games, parameters, true)?;
+    print_standings(&specs, &wins, &total_placement, n_games);
+
+    if args.flag("--baselines") {
+        // every bot on its own against the simple ones, which it should beat easily
+        let rules = Rules {
+            n_players: 1 + registry::BASELINES.len(),
+            ..rules
+        };
+
+        let mut wins = Vec::new();
+        let mut total_placement = Vec::new();
+        for spec in specs.iter() {
+            let lineup: Vec<String> = iter::once(spec.clone())
+                .chain(registry::BASELINES.iter().map(|b| b.to_string()))
+                .collect();
+            let (w, p) = rotate_seats(&lineup, rules, seed, n_games, parameters, false)?;
+            wins.push(w[0]);
+            total_placement.push(p[0]);
+        }
+
+        println!();
+        println!("against {}:", registry::BASELINES.join(", "));
+        print_standings(&specs, &wins, &total_placement, n_games);
+    }
+
+    Ok(())
+}
+
+// plays `n_games` between `lineup`, moving everyone along a seat every game,
+// and gives how many times each of them won and the sum of their places
+fn rotate_seats(
+    lineup: &[String],
+    rules: Rules,
+    seed: u64,
+    n_games: usize,
+    parameters: [f64; N_PARAMETERS],
+    verbose: bool,
+) -> Result<(Vec<usize>, Vec<usize>), String> {
+    let n = lineup.len();
+    let mut wins = vec![0; n];
+    let mut total_placement = vec![0; n];
+
+    for g in 0..n_games {
+        // rotate the seats, so that nobody always sits in the same place
+        let order: Vec<usize> = (0..n).map(|seat| (seat + g) % n).collect();
+
+        // and bots that don't name their own seed get one from the game and their seat,
+        // so that the same --seed plays the same games
+        let game_seed = seed.wrapping_add(g as u64);
+        let players = order
+            .iter()
+            .enumerate()
+            .map(|(seat, &bot)| {
+                let bot_seed = game_seed
+                    .wrapping_mul(n as u64 + 1)
+                    .wrapping_add(seat as u64 + 1);
+                make_player(&registry::with_seed(&lineup[bot], bot_seed), parameters)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let result = Match::new(players)
+            .with_rules(rules)
+            .with_seed(game_seed)
+            .run()?;
+
+        for (seat, &place) in result.placements.iter().enumerate() {
+            let bot = order[seat];
+            total_placement[bot] += place;
+            if place == 0 {
+                wins[bot] += 1;
+            }
+        }
+
+        if verbose {
+            println!("game {}: placements {:?}", g, result.placements);
+            for forfeit in result.forfeits.iter() {
+                println!(
+                    "    {} (seat {}) forfeited: {}",
+                    lineup[order[forfeit.seat]], forfeit.seat, forfeit.reason
+                );
+            }
+        }
+    }
+
+    Ok((wins, total_placement))
+}
+
+fn print_standings(names: &[String], wins: &[usize], total_placement: &[usize], n_games: usize) {
+    println!(
+        "{:<40} {:>6} {:>10} {:>14}",
+        "bot", "wins", "win rate", "mean place"
+    );
+    for bot in 0..names.len() {
+        println!(
+            "{:<40} {:>6} {:>10.3} {:>14.3}",
+            names[bot],
+            wins[bot],
+            wins[bot] as f64 / n_games.max(1) as f64,
+            total_placement[bot] as f64 / n_games.max(1) as f64 + 1.0,
+        );
+    }
+}
+
+fn replay(args: &Args) -> Result<(), String> {
+    let path = args
+        .positional
+        .first()
+        .ok_or("usage: pusoy replay <file>")?;
+    let record = GameRecord::load(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+
+    if args.flag("--reveal") {
+        for (seat, hand) in record.hands.iter().enumerate() {
+            let mut hand = hand.clone();
+            hand.sort();
+            println!("seat {} was dealt {:?}", seat, hand);
+        }
+    }
+
+    let mut turn = 0;
+    let game = record
+        .replay(|game, play| {
+            let interface = SafeGameInterface::from_game(game);
+            println!("{:>4}: {}", turn, describe(&interface, play));
+            turn += 1;
+        })
+        .map_err(|e| format!("the record contains an illegal play: {:?}", e))?;
+
+    print_placements(&game);
+
+    Ok(())
+}
+
+fn export(args: &Args) -> Result<(), String> {
+    let path = args
+        .positional
+        .first()
+        .ok_or("usage: pusoy export <file> [--out FILE]")?;
+    let record = GameRecord::load(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+
+    let out = match args.opt("--out") {
+        Some(out) => PathBuf::from(out),
+        None => Path::new(path).with_extension("html"),
+    };
+
+    fs::write(&out, export::export_html(&record)?)
+        .map_err(|e| format!("couldn't write {}: {}", out.display(), e))?;
+    println!("wrote {}", out.display());
+
+    Ok(())
+}
+
+fn analyze(args: &Args) -> Result<(), String> {
+    let rules = args.rules()?;
+    let position = analysis_position(args, rules)?;
+
+    if args.flag("--explain") {
+        // why the heuristic bot would make the play it would
+        let game = GameState::from_position(&position)?;
+        let player = MachinePlayer::with_budget(args.parameters()?, args.budget()?);
+        let (_play, report) = player.choose_play_with_report(&SafeGameInterface::from_game(&game));
+
+        if args.flag("--json") {
+            println!("{}", report.to_json());
+        } else {
+            print!("{}", report);
+        }
+        return Ok(());
+    }
+
+    let evaluator = match args.opt("--evaluator").unwrap_or("heuristic") {
+        "heuristic" => Evaluator::Heuristic(args.parameters()?),
+        "mcts" => Evaluator::Mcts { seed: args.seed()? },
+        "exact" => {
+            if args.opt("--opponents").is_none() {
+                return Err(
+                    "exact analysis needs everyone's hand, given with --opponents".to_string(),
+                );
+            }
+            Evaluator::Exact
+        }
+        other => return Err(format!("unknown evaluator: {}", other)),
+    };
+
+    let scored = analysis::analyze(&position, &evaluator, args.budget()?)?;
+    let report = AnalysisReport::new(&evaluator, 0, scored);
+
+    if args.flag("--json") {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report);
+    }
+
+    Ok(())
+}
+
+fn analysis_position(args: &Args, rules: Rules) -> Result<Position, String> {
+    // we are in seat 0 and it is our turn. whatever is on the table was played by --owner
+    // (the seat before us unless given). the cards we can't see go to the others, evenly unless
+    // --sizes says how many each of them holds, or exactly as --opponents says
+    let hand = args
+        .opt("--hand")
+        .or_else(|| args.positional.first().map(|s| s.as_str()))
+        .ok_or("usage: pusoy analyze --hand \"3C 4D 4S TH\" [--table \"9S\"]")?;
+    let hand = parse_cards(hand)?;
+    let table = args.opt("--table").map(parse_cards).transpose()?;
+
+    let n = rules.n_players;
+    let mut position = Position::new(rules).with_hand(0, hand.clone()).with_turn(0);
+
+    let mut rest: Vec<Card> = entire_deck()
+        .into_iter()
+        .filter(|c| !hand.contains(c) && !table.iter().flatten().any(|t| t == c))
+        .collect();
+
+    if let Some(table) = table {
+        let owner = args.get("--owner", n - 1)?;
+        position = position.with_table(owner, table);
+    } else if !hand.contains(&THREE_OF_CLUBS) {
+        // a clean table without the 3♣ means we have the lead later in the game, so the 3♣ has been played
+        rest.retain(|&c| c != THREE_OF_CLUBS);
+    }
+
+    let opponents: Vec<Vec<Card>> = match (args.opt("--opponents"), args.opt("--sizes")) {
+        (Some(hands), _) => hands
+            .split('|')
+            .map(parse_cards)
+            .collect::<Result<_, _>>()?,
+        (None, Some(sizes)) => {
+            let mut hands = Vec::new();
+            for size in sizes.split(',') {
+                let size: usize = size
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("bad size: {}", size))?;
+                if size > rest.len() {
+                    return Err("there aren't enough cards left for --sizes".to_string());
+                }
+                hands.push(rest.split_off(rest.len() - size));
+            }
+            hands
+        }
+        (None, None) => {
+            let mut hands = vec![Vec::new(); n - 1];
+            for (i, &card) in rest.iter().enumerate() {
+                hands[i % (n - 1)].push(card);
+            }
+            hands
+        }
+    };
+
+    if opponents.len() != n - 1 {
+        return Err(format!(
+            "{} other hands given for {} other players",
+            opponents.len(),
+            n - 1
+        ));
+    }
+
+    let finished = (1..n)
+        .filter(|&seat| opponents[seat - 1].is_empty())
+        .collect();
+    for (i, opponent) in opponents.into_iter().enumerate() {
+        position = position.with_hand(i + 1, opponent);
+    }
+
+    Ok(position.with_finished(finished))
+}
+
+fn stats(args: &Args) -> Result<(), String> {
+    let mut stats = Stats::new();
+
+    let mut paths = Vec::new();
+    for path in args.positional.iter().map(PathBuf::from) {
+        if path.is_dir() {
+            let entries = fs::read_dir(&path)
+                .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+            for entry in entries {
+                let entry = entry.map_err(|e| e.to_string())?.path();
+                if entry.extension().is_some_and(|x| x == "json") {
+                    paths.push(entry);
+                }
+            }
+        } else {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    for path in paths.iter() {
+        let record = GameRecord::load(path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        stats
+            .add_record(&record)
+            .map_err(|e| format!("{} contains an illegal play: {:?}", path.display(), e))?;
+    }
+
+    if let Some(path) = args.opt("--events") {
+        let file = File::open(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+        let mut input = io::BufReader::new(file);
+        while let Some(event) = protocol::receive::<GameEvent>(&mut input)
+            .map_err(|e| format!("couldn't read {}: {}", path, e))?
+        {
+            stats.on_event(&event);
+        }
+    }
+
+    if paths.is_empty() && args.opt("--events").is_none() {
+        let rules = args.rules()?;
+        let seed = args.seed()?;
+        let n_games: u64 = args.get("--games", 100)?;
+        let specs = args.bots("machine");
+
+        for g in 0..n_games {
+            let players = make_players(&specs, rules.n_players, args.parameters()?)?;
+            run_game(
+                &players,
+                GameState::with_rules(rules, shuffled_deck(seed.wrapping_add(g))),
+                &mut [&mut stats],
+            );
+        }
+    }
+
+    let summary = stats.summary();
+    let text = match args.opt("--format").unwrap_or("csv") {
+        "csv" => summary.to_csv(),
+        "json" => summary.to_json() + "\n",
+        other => return Err(format!("unknown format: {}", other)),
+    };
+
+    match args.opt("--out") {
+        Some(out) => fs::write(out, text).map_err(|e| format!("couldn't write {}: {}", out, e)),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+fn review(args: &Args) -> Result<(), String> {
+    let path = args
+        .positional
+        .first()
+        .ok_or("usage: pusoy review <file> --seat N")?;
+    let record = GameRecord::load(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+
+    let default = ReviewConfig::default();
+    let config = ReviewConfig {
+        seat: args.get("--seat", default.seat)?,
+        threshold: args.get("--threshold", default.threshold)?,
+        iterations: args.get("--iterations", default.iterations)?,
+        time_limit: args.budget()?.time_limit,
+        seed: args.seed()?,
+    };
+
+    let review = review::review(&record, &config)?;
+
+    if let Some(html) = args.opt("--html") {
+        fs::write(html, review.to_html()).map_err(|e| format!("couldn't write {}: {}", html, e))?;
+    }
+
+    if args.flag("--json") {
+        println!("{}", review.to_json());
+    } else {
+        print!("{}", review);
+    }
+
+    Ok(())
+}
+
+fn hand_strength(args: &Args) -> Result<(), String> {
+    let hand = args
+        .opt("--hand")
+        .or_else(|| args.positional.first().map(|s| s.as_str()))
+        .ok_or("usage: pusoy strength --hand \"3C 4D 4S TH ...\"")?;
+    let evaluation = strength::hand_strength(&parse_cards(hand)?)?;
+
+    if args.flag("--json") {
+        println!("{}", serde_json::to_string_pretty(&evaluation).unwrap());
+    } else {
+        print!("{}", evaluation);
+    }
+
+    Ok(())
+}
+
+fn partition(args: &Args) -> Result<(), String> {
+    let hand = args
+        .opt("--hand")
+        .or_else(|| args.positional.first().map(|s| s.as_str()))
+        .ok_or("usage: pusoy partition --hand \"3C 4D 4S TH ...\" [--top K]")?;
+    let hand = parse_cards(hand)?;
+    let k = args.get("--top", 5)?;
+    let objective = match args.opt("--objective").unwrap_or("fewest-plays") {
+        "fewest-plays" => Objective::FewestPlays,
+        "fewest-low-singles" => Objective::FewestLowSingles,
+        other => return Err(format!("unknown objective: {}", other)),
+    };
+
+    let partitions = partition::best_partitions(&hand, k, objective)?;
+
+    if args.flag("--json") {
+        println!("{}", serde_json::to_string_pretty(&partitions).unwrap());
+    } else {
+        println!(
+            "the best {} ways to split the hand, for {}",
+            partitions.len(),
+            objective.name()
+        );
+        for (rank, partition) in partitions.iter().enumerate() {
+            println!("{:>3}. {}", rank + 1, partition);
+        }
+    }
+
+    Ok(())
+}
+
+fn threats(args: &Args) -> Result<(), String> {
+    let rules = args.rules()?;
+    let hand = args
+        .opt("--hand")
+        .or_else(|| args.positional.first().map(|s| s.as_str()))
+        .ok_or("usage: pusoy threats --hand \"3C 4D 4S TH\" [--play \"9H 9S\"]")?;
+    let hand = parse_cards(hand)?;
+    let played = parse_cards(args.opt("--played").unwrap_or(""))?;
+
+    let plays = match args.opt("--play") {
+        Some(cards) => {
+            let cards = parse_cards(cards)?;
+            vec![Play::infer_from_cards(cards).ok_or("--play isn't a play")?]
+        }
+        None => {
+            // the best of every kind we could play
+            let mut best: Vec<Play> = Vec::new();
+            for play in Finder::new(hand.clone()).all_plays() {
+                match best.iter_mut().find(|b| b.kind() == play.kind()) {
+                    Some(b) if *b < play => *b = play,
+                    Some(_) => {}
+                    None => best.push(play),
+                }
+            }
+            best.sort();
+            best
+        }
+    };
+
+    let seen: Vec<Card> = hand
+        .iter()
+        .chain(played.iter())
+        .chain(plays.iter().flat_map(|p| p.cards()))
+        .cloned()
+        .collect();
+    let unseen: Vec<Card> = entire_deck()
+        .into_iter()
+        .filter(|c| !seen.contains(c))
+        .collect();
+
+    // we are seat 0, and the others split what we can't see evenly unless --sizes says otherwise
+    let n_others = rules.n_players - 1;
+    let sizes: Vec<usize> = match args.opt("--sizes") {
+        Some(sizes) => sizes
+            .split(',')
+            .map(|s| s.trim().parse().map_err(|_| format!("bad size: {}", s)))
+            .collect::<Result<_, _>>()?,
+        None => (0..n_others)
+            .map(|i| unseen.len() / n_others + (i < unseen.len() % n_others) as usize)
+            .collect(),
+    };
+    if sizes.len() != n_others {
+        return Err(format!(
+            "{} sizes given for {} other players",
+            sizes.len(),
+            n_others
+        ));
+    }
+
+    let threats = Threats::new(
+        unseen,
+        sizes
+            .into_iter()
+            .enumerate()
+            .map(|(i, s)| (i + 1, s))
+            .collect(),
+    )?;
+    let samples = args.get("--samples", threat::DEFAULT_SAMPLES)?;
+    let mut rng = StdRng::seed_from_u64(args.seed()?);
+
+    let results: Vec<_> = plays
+        .iter()
+        .map(|play| threats.probability(play, samples, &mut rng))
+        .collect();
+
+    if args.flag("--json") {
+        println!("{}", serde_json::to_string_pretty(&results).unwrap());
+    } else {
+        for threat in results {
+            println!("{}", threat);
+        }
+    }
+
+    Ok(())
+}
+
+fn bench(args: &Args) -> Result<(), String> {
+    let rules = args.rules()?;
+    let seed = args.seed()?;
+    let n_games: u64 = args.get("--games", 3)?;
+
+    if args.flag("--check-search") {
+        return check_search(rules, seed, n_games, args.parameters()?);
+    }
+    let specs = args.bots("machine");
+
+    let mut times = Vec::new();
+
+    for g in 0..n_games {
+        let players = make_players(&specs, rules.n_players, args.parameters()?)?;
+
+        let result = Match::new(players)
+            .with_rules(rules)
+            .with_seed(seed.wrapping_add(g))
+            .run()?;
+        times.extend(result.decisions.iter().map(|d| d.elapsed));
+    }
+
+    if times.is_empty() {
+        return Ok(());
+    }
+
+    times.sort();
+    let total: Duration = times.iter().sum();
+
+    println!("decisions: {}", times.len());
+    println!("mean:      {:?}", total / times.len() as u32);
+    println!("median:    {:?}", times[times.len() / 2]);
+    println!("max:       {:?}", times[times.len() - 1]);
+    println!("total:     {:?}", total);
+
+    Ok(())
+}
+
+// plays machine bots against each other, and at every decision makes sure that the search
+// gives the same mean cost for every first play with and without its memo
+fn check_search(
+    rules: Rules,
+    seed: u64,
+    n_games: u64,
+    parameters: [f64; N_PARAMETERS],
+) -> Result<(), String> {
+    let bot = MachinePlayer::new(parameters);
+    let mut positions = 0;
+    let mut first_plays = 0;
+    let mut largest: f64 = 0.0;
+
+    for g in 0..n_games {
+        let mut game = GameState::with_rules(rules, shuffled_deck(seed.wrapping_add(g)));
+
+        while !game.is_over() {
+            let interface = SafeGameInterface::from_game(&game);
+
+            for (play, with, without) in bot.compare_memo(&interface) {
+                // some first plays cost infinitely much, with the memo or without it
+                let difference = if with == without {
+                    0.0
+                } else {
+                    (with - without).abs() / with.abs().max(1.0)
+                };
+                if difference.is_nan() || difference >= 1e-9 {
+                    return Err(format!(
+                        "the memo changed the mean cost of {} from {} to {}",
+                        play, without, with
+                    ));
+                }
+                largest = largest.max(difference);
+                first_plays += 1;
+            }
+            positions += 1;
+
+            game.play(bot.choose_play(&interface));
+        }
+    }
+
+    println!(
+        "positions: {}, first plays: {}, largest relative difference: {:e}",
+        positions, first_plays, largest
+    );
+    Ok(())
+}
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+const DEFAULT_WEB_ADDRESS: &str = "127.0.0.1:8080";
+
+fn serve(args: &Args) -> Result<(), String> {
+    let rules = args.rules()?;
+    let n_tables: usize = args.get("--tables", 1)?;
+    let n_remote: usize = args.get("--remote", 1)?;
+    let address = args.opt("--address").unwrap_or(DEFAULT_ADDRESS);
+    let parameters = args.parameters()?;
+
+    if n_remote > rules.n_players {
+        return Err(format!(
+            "{} remote seats for {} players",
+            n_remote, rules.n_players
+        ));
+    }
+
+    // the bots are made on the table threads, so check the specs here while we can still complain
+    let specs = args.bots("machine");
+    make_players(&specs, rules.n_players - n_remote, parameters)?;
+
+    let mut tables = Vec::new();
+    for t in 0..n_tables {
+        let mut seats = Vec::new();
+        for seat in 0..rules.n_players {
+            if seat < n_remote {
+                seats.push(SeatConfig::Remote);
+            } else {
+                let spec = match specs.len() {
+                    1 => specs[0].clone(),
+                    _ => specs[seat - n_remote].clone(),
+                };
+                let make_bot: BotFactory =
+                    Arc::new(move || make_player(&spec, parameters).unwrap());
+                seats.push(SeatConfig::Bot(make_bot));
+            }
+        }
+
+        tables.push(TableConfig {
+            name: format!("table{}", t),
+            rules,
+            seats,
+        });
+    }
+
+    let config = ServerConfig {
+        tables,
+        reconnect_timeout: Duration::from_secs(args.get("--reconnect-timeout", 60)?),
+        between_games: Duration::from_secs(args.get("--between-games", 5)?),
+    };
+
+    let listener =
+        TcpListener::bind(address).map_err(|e| format!("couldn't listen on {}: {}", address, e))?;
+    println!("serving {} table(s) on {}", n_tables, address);
+
+    let web = match args.opt("--web").unwrap_or(DEFAULT_WEB_ADDRESS) {
+        "off" => None,
+        web => {
+            let listener =
+                TcpListener::bind(web).map_err(|e| format!("couldn't listen on {}: {}", web, e))?;
+            println!("open http://{}/ to play in a browser", web);
+            Some(listener)
+        }
+    };
+
+    server::serve(listener, web, config).map_err(|e| e.to_string())
+}
+
+fn connect(args: &Args) -> Result<(), String> {
+    let seat = match args.opt("--seat") {
+        Some(_) => Some(args.get("--seat", 0)?),
+        None => None,
+    };
+
+    client::connect(ClientConfig {
+        address: args.opt("--address").unwrap_or(DEFAULT_ADDRESS).to_string(),
+        table: args.opt("--table").unwrap_or("table0").to_string(),
+        seat,
+        token: args.opt("--token").map(|t| t.to_string()),
+        auto: args.flag("--auto"),
+        assist: args.flag("--assist"),
+    })
+    .map_err(|e| e.to_string())
+}
+
+fn run_engine(args: &Args) -> Result<(), String> {
+    let spec = args.opt("--bots").unwrap_or("machine");
+    let player = make_player(spec, args.parameters()?)?;
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    engine::run_engine(&*player, stdin.lock(), stdout.lock()).map_err(|e| e.to_string())
+}
@@ -0,0 +1,204 @@
+use std::io::{self, BufRead, BufReader};
+use std::net::TcpStream;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use crate::card::parse_cards;
+use crate::game::Observation;
+use crate::protocol::{receive, send, ClientMessage, ServerMessage};
+
+// a terminal client for `server`. it prints what happens at the table and asks for a play on our turn.
+// with `auto` set it just makes the first legal play it is offered, which is handy for filling seats
+// and for trying the server out locally
+
+const RECONNECT_ATTEMPTS: usize = 10;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+pub struct ClientConfig {
+    pub address: String,
+    pub table: String,
+    pub seat: Option<usize>,
+    pub token: Option<String>, // rejoin with this instead of taking a new seat
+    pub auto: bool,
+    pub assist: bool, // ask for hints and warnings
+}
+
+/// Plays at a table until the server goes away
+pub fn connect(config: ClientConfig) -> io::Result<()> {
+    let mut token = config.token.clone();
+    let mut attempts = 0;
+
+    loop {
+        let stream = match TcpStream::connect(&config.address) {
+            Ok(stream) => stream,
+            Err(e) if token.is_some() && attempts < RECONNECT_ATTEMPTS => {
+                eprintln!("couldn't connect ({}), trying again", e);
+                attempts += 1;
+                thread::sleep(RECONNECT_DELAY);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        attempts = 0;
+
+        let hello = match token {
+            Some(ref token) => ClientMessage::Rejoin {
+                table: config.table.clone(),
+                token: token.clone(),
+            },
+            None => ClientMessage::Join {
+                table: config.table.clone(),
+                seat: config.seat,
+            },
+        };
+
+        // we only come back around if the connection dropped after we had a seat
+        match session(&config, stream, hello, &mut token) {
+            Err(ref e) if token.is_some() && e.kind() != io::ErrorKind::Other => {
+                eprintln!("lost the connection ({}), rejoining", e)
+            }
+            result => return result,
+        }
+    }
+}
+
+fn session(
+    config: &ClientConfig,
+    stream: TcpStream,
+    hello: ClientMessage,
+    token: &mut Option<String>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    send(&mut writer, &hello)?;
+
+    let mut seat = None;
+    let mut last_turn: Option<Observation> = None;
+
+    loop {
+        let message = match receive::<ServerMessage>(&mut reader)? {
+            Some(message) => message,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::ConnectionAborted,
+                    "server hung up",
+                ))
+            }
+        };
+
+        match message {
+            ServerMessage::Welcome {
+                table,
+                seat: s,
+                token: t,
+            } => {
+                seat = Some(s);
+                println!("sitting at {} in seat {} (token {})", table, s, t);
+                *token = Some(t);
+                if config.assist {
+                    send(&mut writer, &ClientMessage::Assist { on: true })?;
+                }
+            }
+            ServerMessage::Waiting { missing } => {
+                println!("waiting for {} more player(s)", missing)
+            }
+            ServerMessage::GameStarted { game, observation } => {
+                println!("game {} started", game);
+                print_hand(&observation);
+            }
+            ServerMessage::State { .. } => {}
+            ServerMessage::YourTurn { observation } => {
+                let reply = choose(config.auto, &observation)?;
+                last_turn = Some(observation);
+                send(&mut writer, &reply)?;
+            }
+            ServerMessage::Hint { hint } => print!("{}", hint),
+            ServerMessage::Warning { warnings } => {
+                for warning in warnings {
+                    println!("careful: {}", warning);
+                }
+                println!("make the same play again to play it anyway");
+                if let Some(ref observation) = last_turn {
+                    let reply = choose(config.auto, observation)?;
+                    send(&mut writer, &reply)?;
+                }
+            }
+            ServerMessage::Played { seat: s, play } => {
+                let who = if Some(s) == seat {
+                    "you".to_string()
+                } else {
+                    format!("seat {}", s)
+                };
+                if play.is_pass() {
+                    println!("{} passed", who);
+                } else {
+                    println!("{} played {}", who, play);
+                }
+            }
+            ServerMessage::Rejected { reason } => {
+                println!("you can't play that: {}", reason);
+                if let Some(ref observation) = last_turn {
+                    let reply = choose(config.auto, observation)?;
+                    send(&mut writer, &reply)?;
+                }
+            }
+            ServerMessage::GameOver { placements } => match seat {
+                Some(s) => println!("game over, you came in place {}", placements[s] + 1),
+                None => println!("game over"),
+            },
+            ServerMessage::Error { message } => {
+                // without a seat there is nothing left for us to do
+                if seat.is_none() {
+                    return Err(io::Error::other(message));
+                }
+                println!("error: {}", message);
+            }
+        }
+    }
+}
+
+fn print_hand(observation: &Observation) {
+    let hand: Vec<String> = observation.hand.iter().map(|c| c.to_string()).collect();
+    println!("your hand: {}", hand.join(" "));
+}
+
+fn choose(auto: bool, observation: &Observation) -> io::Result<ClientMessage> {
+    if auto {
+        let play = observation
+            .legal_plays
+            .iter()
+            .find(|p| !p.is_pass())
+            .or_else(|| observation.legal_plays.first());
+
+        return Ok(match play {
+            Some(play) => ClientMessage::Play {
+                cards: play.cards().to_vec(),
+            },
+            None => ClientMessage::Pass,
+        });
+    }
+
+    print_hand(observation);
+    match observation.table {
+        Some(ref play) if !observation.have_control => println!("on the table: {}", play),
+        _ => println!("you have control"),
+    }
+
+    let stdin = io::stdin();
+
+    loop {
+        println!("your play (blank to pass):");
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            process::exit(1);
+        }
+
+        match parse_cards(&line) {
+            Ok(ref cards) if cards.is_empty() => return Ok(ClientMessage::Pass),
+            Ok(cards) => return Ok(ClientMessage::Play { cards }),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use crate::bot::Player;
+use crate::card::{entire_deck, Rank};
+use crate::events::{GameEvent, Observer};
+use crate::game::{GameState, Rules, SafeGameInterface};
+use crate::play::{Play, PlayKind};
+use crate::runner::run_game;
+
+// how likely a play is to win its trick (nobody beats it, so whoever made it gets control),
+// measured by watching self-play. this is what `bot::would_get_control` used to guess at.
+// a situation is the kind of play, its ranking card, how many cards had been played before it,
+// and how many cards the smallest hand among the other players still in the game held.
+// the last two are bucketed, so that every situation comes up often enough to say something
+
+/// Bumped whenever the situations or the file format change, so old tables aren't misread
+pub const TABLE_VERSION: u32 = 1;
+
+// the table that `MachinePlayer` uses unless it is given another one, made from 3000 games with
+//     pusoy train --mode control --games 3000 --seed 1 \
+//         --bots "mcts:iters=200,seed=1 greedy-lowest conservative pass-avoiding"
+// the players have to actually fight over tricks for the table to mean anything.
+// machine bots against each other pass so often that even a lone 3♣ lead always won its trick
+const BUILT_IN: &str = include_str!("../control.json");
+
+// below this many samples, a situation leans on the less specific numbers
+const MIN_SAMPLES: f64 = 20.0;
+
+const N_PLAYED_BUCKETS: usize = 4;
+
+/// What a player could see when a play was made, for looking up how likely it is to win the trick
+#[derive(Clone, Copy, Debug)]
+pub struct Situation {
+    pub played: usize,        // cards played before this play
+    pub smallest_hand: usize, // of the other players who haven't gone out
+}
+
+impl Default for Situation {
+    fn default() -> Situation {
+        // the start of a four player game
+        Situation {
+            played: 0,
+            smallest_hand: 13,
+        }
+    }
+}
+
+impl Situation {
+    pub fn of(game: &SafeGameInterface) -> Situation {
+        let me = game.my_seat();
+        let smallest_hand = game
+            .hand_sizes()
+            .into_iter()
+            .enumerate()
+            .filter(|&(seat, size)| seat != me && size > 0)
+            .map(|(_, size)| size)
+            .min()
+            .unwrap_or(0);
+
+        Situation {
+            played: game.played_cards().len(),
+            smallest_hand,
+        }
+    }
+
+    fn buckets(self) -> (usize, usize) {
+        let played = (self.played / 13).min(N_PLAYED_BUCKETS - 1);
+        let hand = match self.smallest_hand {
+            0..=1 => 0,
+            2 => 1,
+            3..=4 => 2,
+            5..=7 => 3,
+            _ => 4,
+        };
+        (played, hand)
+    }
+}
+
+// (kind, ranking card, played bucket, smallest hand bucket)
+type Key = (PlayKind, usize, usize, usize);
+
+#[derive(Clone, Copy, Default)]
+struct Count {
+    wins: u32,
+    total: u32,
+}
+
+impl Count {
+    fn add(&mut self, other: Count) {
+        self.wins += other.wins;
+        self.total += other.total;
+    }
+}
+
+#[derive(Default)]
+pub struct ControlTable {
+    games: usize,
+    counts: HashMap<Key, Count>,
+    by_card: HashMap<(PlayKind, usize), Count>, // every situation for a play added together
+}
+
+// how the table is saved: (kind, ranking card as `Card::numeric_value`, played bucket, hand bucket, wins, total)
+#[derive(Serialize, Deserialize)]
+struct TableFile {
+    version: u32,
+    games: usize,
+    cells: Vec<(PlayKind, usize, usize, usize, u32, u32)>,
+}
+
+impl ControlTable {
+    /// The table that ships with the program, made with `pusoy train --mode control`
+    pub fn built_in() -> Arc<ControlTable> {
+        static TABLE: OnceLock<Arc<ControlTable>> = OnceLock::new();
+        TABLE
+            .get_or_init(|| {
+                let file = serde_json::from_str(BUILT_IN).expect("control.json is valid json");
+                Arc::new(ControlTable::from_file(file).expect("control.json is up to date"))
+            })
+            .clone()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<ControlTable> {
+        let reader = BufReader::new(File::open(path)?);
+        let file = serde_json::from_reader(reader)?;
+        ControlTable::from_file(file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut cells: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(&(kind, card, played, hand), count)| {
+                (kind, card, played, hand, count.wins, count.total)
+            })
+            .collect();
+        cells.sort_by_key(|&(kind, card, played, hand, _, _)| (kind, card, played, hand));
+
+        let file = TableFile {
+            version: TABLE_VERSION,
+            games: self.games,
+            cells,
+        };
+
+        let writer = BufWriter::new(File::create(path)?);
+        Ok(serde_json::to_writer(writer, &file)?)
+    }
+
+    fn from_file(file: TableFile) -> Result<ControlTable, String> {
+        if file.version != TABLE_VERSION {
+            return Err(format!(
+                "the control table is version {}, but we need version {}",
+                file.version, TABLE_VERSION
+            ));
+        }
+
+        let mut table = ControlTable {
+            games: file.games,
+            ..ControlTable::default()
+        };
+        for (kind, card, played, hand, wins, total) in file.cells {
+            table.add((kind, card, played, hand), Count { wins, total });
+        }
+
+        Ok(table)
+    }
+
+    fn add(&mut self, key: Key, count: Count) {
+        let (kind, card, _, _) = key;
+        self.counts.entry(key).or_default().add(count);
+        self.by_card.entry((kind, card)).or_default().add(count);
+    }
+
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    /// How likely `play` is to win its trick, when it's made in `situation`
+    pub fn probability(&self, play: &Play, situation: Situation) -> f64 {
+        let card = play
+            .ranking_card()
+            .expect("passes don't win tricks")
+            .numeric_value();
+        let (played, hand) = situation.buckets();
+
+        // with too few samples, lean towards every situation for this play together,
+        // and with too few of those, towards the old rule of thumb
+        let guess = if would_get_control(play) { 1.0 } else { 0.0 };
+        let overall = blend(self.by_card.get(&(play.kind(), card)), guess);
+        blend(self.counts.get(&(play.kind(), card, played, hand)), overall)
+    }
+}
+
+fn blend(count: Option<&Count>, prior: f64) -> f64 {
+    let count = count.cloned().unwrap_or_default();
+    (count.wins as f64 + MIN_SAMPLES * prior) / (count.total as f64 + MIN_SAMPLES)
+}
+
+fn would_get_control(play: &Play) -> bool {
+    // the rule of thumb from before there was any data
+    match play.kind() {
+        PlayKind::Pass => unimplemented!(),
+        PlayKind::Single | PlayKind::Pair => {
+            let rank = play.ranking_card().unwrap().rank;
+
+            rank == Rank::Two || rank == Rank::Ace
+        }
+        PlayKind::Strait => false,
+        PlayKind::Flush => false,
+        PlayKind::FullHouse => true,
+        PlayKind::FourOfAKind => true,
+        PlayKind::StraitFlush => true,
+    }
+}
+
+/// Watches games and counts which plays won their tricks
+#[derive(Default)]
+pub struct TableBuilder {
+    table: ControlTable,
+
+    // the game that is being watched right now
+    hand_sizes: Vec<usize>,
+    played: usize,
+    top: Option<(Key, usize)>, // the play on top of the trick, and who made it
+}
+
+impl TableBuilder {
+    pub fn new() -> TableBuilder {
+        TableBuilder::default()
+    }
+
+    pub fn finish(self) -> ControlTable {
+        self.table
+    }
+
+    fn settle(&mut self, won: bool) {
+        if let Some((key, _)) = self.top.take() {
+            let count = Count {
+                wins: won as u32,
+                total: 1,
+            };
+            self.table.add(key, count);
+        }
+    }
+
+    fn merge(mut self, other: TableBuilder) -> TableBuilder {
+        self.table.games += other.table.games;
+        for (key, count) in other.table.counts {
+            self.table.add(key, count);
+        }
+        self
+    }
+}
+
+impl Observer for TableBuilder {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Dealt { hands, .. } => {
+                self.hand_sizes = hands.iter().map(|h| h.len()).collect();
+                self.played = 0;
+                self.top = None;
+            }
+            GameEvent::Played { seat, play } => {
+                // whatever was on top got beaten
+                self.settle(false);
+
+                let smallest_hand = self
+                    .hand_sizes
+                    .iter()
+                    .enumerate()
+                    .filter(|&(other, &size)| other != *seat && size > 0)
+                    .map(|(_, &size)| size)
+                    .min()
+                    .unwrap_or(0);
+                let situation = Situation {
+                    played: self.played,
+                    smallest_hand,
+                };
+                let (played, hand) = situation.buckets();
+                let card = play.ranking_card().unwrap().numeric_value();
+                self.top = Some(((play.kind(), card, played, hand), *seat));
+
+                self.played += play.cards().len();
+                self.hand_sizes[*seat] -= play.cards().len();
+            }
+            GameEvent::TrickCleared => self.settle(true),
+            GameEvent::GameOver { .. } => {
+                // going out with a play wins its trick, but if the game just stopped there we can't tell
+                if let Some((_, seat)) = self.top {
+                    let went_out = self.hand_sizes[seat] == 0;
+                    if went_out {
+                        self.settle(true);
+                    }
+                }
+                self.top = None;
+                self.table.games += 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Builds a table by watching `n_games` games between the players that `make_players` makes
+pub fn build_table(
+    rules: Rules,
+    n_games: usize,
+    seed: u64,
+    make_players: impl Fn() -> Vec<Box<dyn Player>> + Sync,
+) -> ControlTable {
+    (0..n_games)
+        .into_par_iter()
+        .map(|g| {
+            let mut deck = entire_deck();
+            deck.shuffle(&mut StdRng::seed_from_u64(seed.wrapping_add(g as u64)));
+
+            let mut builder = TableBuilder::new();
+            run_game(
+                &make_players(),
+                GameState::with_rules(rules, deck),
+                &mut [&mut builder],
+            );
+            builder
+        })
+        .reduce(TableBuilder::new, TableBuilder::merge)
+        .finish()
+}
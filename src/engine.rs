@@ -0,0 +1,434 @@
+use std::cell::RefCell;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bot::Player;
+use crate::card::{entire_deck, parse_cards, Card, Suit};
+use crate::game::{GameState, Rules, SafeGameInterface};
+use crate::play::Play;
+use crate::runner::stand_in;
+
+// a text protocol for bots that run as their own program, in the spirit of uci for chess engines.
+// the host writes lines to the engine's stdin, and the engine answers on its stdout.
+// cards are written like 3C TD AS 2H (rank then suit letter), and stderr is left alone for the engine's own logging
+//
+// host -> engine
+//     pusoy                      sent once at startup, answer with ready
+//     newgame <players> <seat> [play-to-end]
+//                                a new game is starting, and we are sitting in <seat>
+//     played <seat> <cards>      a play that was made, or `played <seat> pass`.
+//                                every play of the game is sent in order before the next go
+//     hand <cards>               the cards we are holding now
+//     table <cards>              what we have to beat, or `table none` when we can play anything
+//     legal <play> | <play> ...  every legal play, for bots that don't want to work that out themselves
+//     go <milliseconds>          answer with play within this long
+//     illegal <reason>           the last answer wasn't allowed, so the host played for us
+//     quit
+//
+// engine -> host
+//     ready                      the engine is set up
+//     play <cards>               or `play pass`
+//     info <anything>            ignored, along with anything else the host doesn't understand
+//
+// the host starts the engine up again if it crashes, sending newgame and all of the plays again,
+// so an engine never has to remember anything between games
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// how long an engine gets to start up
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Plays by asking a program that speaks the engine protocol
+pub struct SubprocessPlayer {
+    program: String,
+    args: Vec<String>,
+    timeout: Duration,
+    state: RefCell<Session>,
+}
+
+struct Session {
+    engine: Option<Engine>,
+    sent: usize,      // how many plays of this game the engine has been told about
+    hand_size: usize, // so we can notice a new game starting
+}
+
+enum Failure {
+    TimedOut,
+    Crashed(String),
+}
+
+impl SubprocessPlayer {
+    pub fn new(program: &str, args: &[String], timeout: Duration) -> SubprocessPlayer {
+        SubprocessPlayer {
+            program: program.to_string(),
+            args: args.to_vec(),
+            timeout,
+            state: RefCell::new(Session {
+                engine: None,
+                sent: 0,
+                hand_size: 0,
+            }),
+        }
+    }
+
+    fn ask(&self, session: &mut Session, game: &SafeGameInterface) -> Result<String, Failure> {
+        let history: Vec<(usize, &Play)> = game.history().collect();
+
+        let new_game = session.engine.is_none()
+            || session.sent > history.len()
+            || game.my_hand().len() > session.hand_size;
+
+        if session.engine.is_none() {
+            let engine = Engine::start(&self.program, &self.args)
+                .map_err(|e| Failure::Crashed(format!("couldn't start {}: {}", self.program, e)))?;
+            session.engine = Some(engine);
+        }
+
+        let engine = session.engine.as_mut().unwrap();
+        let mut lines = Vec::new();
+
+        if new_game {
+            let rules = game.rules();
+            lines.push(format!(
+                "newgame {} {}{}",
+                rules.n_players,
+                game.my_seat(),
+                if rules.play_to_end {
+                    " play-to-end"
+                } else {
+                    ""
+                }
+            ));
+            session.sent = 0;
+        }
+
+        for &(seat, play) in history[session.sent..].iter() {
+            lines.push(format!("played {} {}", seat, play_text(play)));
+        }
+        session.sent = history.len();
+        session.hand_size = game.my_hand().len();
+
+        let mut hand = game.my_hand().to_vec();
+        hand.sort();
+        lines.push(format!("hand {}", cards_text(&hand)));
+
+        match game.get_play_on_table() {
+            Some(play) if !game.have_control() && !game.is_first_turn() => {
+                lines.push(format!("table {}", cards_text(play.cards())))
+            }
+            _ => lines.push("table none".to_string()),
+        }
+
+        let legal: Vec<String> = game.legal_plays().iter().map(play_text).collect();
+        lines.push(format!("legal {}", legal.join(" | ")));
+        lines.push(format!("go {}", self.timeout.as_millis()));
+
+        for line in lines {
+            engine
+                .send(&line)
+                .map_err(|e| Failure::Crashed(e.to_string()))?;
+        }
+
+        engine.reply("play", self.timeout)
+    }
+}
+
+impl Player for SubprocessPlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        let mut session = self.state.borrow_mut();
+
+        // one restart if it crashes, after that it just loses its turn
+        for _ in 0..2 {
+            let answer = match self.ask(&mut session, game) {
+                Ok(answer) => answer,
+                Err(Failure::TimedOut) => {
+                    eprintln!("{} took too long, playing for it", self.program);
+                    session.engine = None;
+                    break;
+                }
+                Err(Failure::Crashed(e)) => {
+                    eprintln!("{} crashed ({}), starting it again", self.program, e);
+                    session.engine = None;
+                    continue;
+                }
+            };
+
+            let checked = parse_play(&answer)
+                .and_then(|cards| game.can_play(cards).map_err(|e| format!("{:?}", e)));
+
+            match checked {
+                Ok(play) => return play,
+                Err(reason) => {
+                    eprintln!(
+                        "{} played {} ({}), playing for it",
+                        self.program, answer, reason
+                    );
+                    if let Some(ref mut engine) = session.engine {
+                        let _ = engine.send(&format!("illegal {}", reason));
+                    }
+                    break;
+                }
+            }
+        }
+
+        stand_in(game)
+    }
+}
+
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Engine {
+    fn start(program: &str, args: &[String]) -> io::Result<Engine> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // reading on another thread is the simplest way to give up on a slow engine
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Engine {
+            child,
+            stdin,
+            lines,
+        };
+
+        engine.send("pusoy")?;
+        match engine.reply("ready", STARTUP_TIMEOUT) {
+            Ok(_) => Ok(engine),
+            Err(Failure::TimedOut) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "never said it was ready",
+            )),
+            Err(Failure::Crashed(e)) => Err(io::Error::other(e)),
+        }
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()
+    }
+
+    fn reply(&mut self, command: &str, timeout: Duration) -> Result<String, Failure> {
+        // the rest of the first line that starts with `command`
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+
+            let line = match self.lines.recv_timeout(left) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => return Err(Failure::TimedOut),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(Failure::Crashed("it stopped talking".to_string()))
+                }
+            };
+
+            let mut words = line.trim().splitn(2, ' ');
+            if words.next() == Some(command) {
+                return Ok(words.next().unwrap_or("").trim().to_string());
+            }
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Speaks the engine protocol on `input` and `output`, letting `player` decide every play
+pub fn run_engine(
+    player: &dyn Player,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut rules = Rules::default();
+    let mut seat = 0;
+    let mut hand = Vec::new();
+    let mut history: Vec<(usize, Vec<Card>)> = Vec::new();
+
+    for line in input.lines() {
+        let line = line?;
+        let mut words = line.trim().splitn(2, ' ');
+        let command = words.next().unwrap_or("");
+        let rest = words.next().unwrap_or("").trim();
+
+        match command {
+            "pusoy" => writeln!(output, "ready")?,
+            "newgame" => {
+                let words: Vec<&str> = rest.split_whitespace().collect();
+                let number = |i: usize| words.get(i).and_then(|w| w.parse().ok());
+
+                match (number(0), number(1)) {
+                    (Some(n_players), Some(s)) => {
+                        rules = Rules {
+                            n_players,
+                            play_to_end: words.get(2) == Some(&"play-to-end"),
+                        };
+                        seat = s;
+                        history.clear();
+                    }
+                    _ => writeln!(output, "info bad newgame: {}", rest)?,
+                }
+            }
+            "played" => {
+                let mut words = rest.splitn(2, ' ');
+                let played_by = words.next().and_then(|w| w.parse().ok());
+                let cards = parse_play(words.next().unwrap_or(""));
+
+                match (played_by, cards) {
+                    (Some(played_by), Ok(cards)) => history.push((played_by, cards)),
+                    _ => writeln!(output, "info bad played: {}", rest)?,
+                }
+            }
+            "hand" => match parse_cards(rest) {
+                Ok(cards) => hand = cards,
+                Err(e) => writeln!(output, "info bad hand: {}", e)?,
+            },
+            "go" => match rebuild(rules, seat, &hand, &history) {
+                Ok(game) => {
+                    let play = player.choose_play(&SafeGameInterface::from_game(&game));
+                    writeln!(output, "play {}", play_text(&play))?;
+                }
+                Err(e) => {
+                    writeln!(output, "info couldn't follow the game: {}", e)?;
+                    writeln!(output, "play pass")?;
+                }
+            },
+            "quit" => break,
+            _ => {} // table, legal, illegal, and whatever else
+        }
+
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+fn rebuild(
+    rules: Rules,
+    seat: usize,
+    hand: &[Card],
+    history: &[(usize, Vec<Card>)],
+) -> Result<GameState, String> {
+    // we can't know how the other cards were dealt, but any deal that agrees with
+    // everything we've been told leads to the same position from where we sit
+    rules.check()?;
+    if seat >= rules.n_players {
+        return Err(format!("there is no seat {}", seat));
+    }
+
+    let mut hands: Vec<Vec<Card>> = vec![Vec::new(); rules.n_players];
+    hands[seat] = hand.to_vec();
+
+    for &(played_by, ref cards) in history.iter() {
+        if played_by >= rules.n_players {
+            return Err(format!("there is no seat {}", played_by));
+        }
+        hands[played_by].extend_from_slice(cards);
+    }
+
+    let mut unseen: Vec<Card> = entire_deck()
+        .into_iter()
+        .filter(|card| !hands.iter().any(|h| h.contains(card)))
+        .collect();
+
+    let hand_size = 52 / rules.n_players;
+    for (s, h) in hands.iter_mut().enumerate() {
+        if s == seat {
+            continue;
+        }
+        let missing = hand_size
+            .checked_sub(h.len())
+            .ok_or_else(|| format!("seat {} played too many cards", s))?;
+        if missing > unseen.len() {
+            return Err("not enough cards to go around".to_string());
+        }
+        h.extend(unseen.drain(..missing));
+    }
+
+    if hands.iter().any(|h| h.len() != hand_size) {
+        return Err("our hand doesn't fit with what has been played".to_string());
+    }
+
+    let mut game = GameState::from_hands(rules, hands);
+
+    for &(played_by, ref cards) in history.iter() {
+        if played_by != game.current_player {
+            return Err(format!("seat {} played out of turn", played_by));
+        }
+        let play = game
+            .can_play(cards.clone())
+            .map_err(|e| format!("seat {} couldn't have played that: {:?}", played_by, e))?;
+        game.play(play);
+    }
+
+    if game.current_player != seat {
+        return Err(format!(
+            "it's seat {}'s turn, not ours",
+            game.current_player
+        ));
+    }
+
+    Ok(game)
+}
+
+fn parse_play(text: &str) -> Result<Vec<Card>, String> {
+    match text.trim() {
+        "pass" => Ok(Vec::new()),
+        text => parse_cards(text),
+    }
+}
+
+fn play_text(play: &Play) -> String {
+    if play.is_pass() {
+        "pass".to_string()
+    } else {
+        cards_text(play.cards())
+    }
+}
+
+fn cards_text(cards: &[Card]) -> String {
+    // the protocol sticks to ascii, so no suit symbols
+    let cards: Vec<String> = cards
+        .iter()
+        .map(|card| {
+            let suit = match card.suit {
+                Suit::Clubs => 'C',
+                Suit::Spades => 'S',
+                Suit::Hearts => 'H',
+                Suit::Diamonds => 'D',
+            };
+            format!("{}{}", card.rank, suit)
+        })
+        .collect();
+
+    cards.join(" ")
+}
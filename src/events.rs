@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use crate::card::Card;
+use crate::game::{GameError, GameRecord, GameState, SafeGameInterface};
+use crate::play::Play;
+
+// what happens over the course of a game, as it happens.
+// the runner in `runner` works these out after every play and hands them to every observer,
+// so that logging, user interfaces, statistics and training don't each have to dig through the record
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameEvent {
+    Dealt { hands: Vec<Vec<Card>>, first: usize },
+    Played { seat: usize, play: Play },
+    Passed { seat: usize },
+    TrickCleared, // everyone passed on the last play
+    ControlGained { seat: usize },
+    PlayerFinished { seat: usize, place: usize },
+    Forfeited { seat: usize, reason: String }, // a bot plays for them from here on
+    GameOver { placements: Vec<usize> },
+}
+
+pub trait Observer {
+    fn on_event(&mut self, event: &GameEvent);
+
+    /// Called with what the player could see when it decided, before the play is made
+    fn on_decision(&mut self, _game: &SafeGameInterface, _play: &Play) {}
+}
+
+impl<F: FnMut(&GameEvent)> Observer for F {
+    fn on_event(&mut self, event: &GameEvent) {
+        self(event)
+    }
+}
+
+pub fn deal_events(game: &GameState) -> Vec<GameEvent> {
+    vec![GameEvent::Dealt {
+        hands: game.dealt().to_vec(),
+        first: game.current_player,
+    }]
+}
+
+/// The events caused by `seat` making `play`, where `game` is the state right after it was made
+/// and `finished_before` is how many players had gone out before it
+pub fn play_events(
+    seat: usize,
+    play: &Play,
+    finished_before: usize,
+    game: &GameState,
+) -> Vec<GameEvent> {
+    let mut events = Vec::new();
+
+    if play.is_pass() {
+        events.push(GameEvent::Passed { seat });
+    } else {
+        events.push(GameEvent::Played {
+            seat,
+            play: play.clone(),
+        });
+    }
+
+    if game.finished().len() > finished_before {
+        events.push(GameEvent::PlayerFinished {
+            seat,
+            place: finished_before,
+        });
+    }
+
+    if game.is_over() {
+        events.push(GameEvent::GameOver {
+            placements: game.placements(),
+        });
+    } else if game.have_control() {
+        events.push(GameEvent::TrickCleared);
+        events.push(GameEvent::ControlGained {
+            seat: game.current_player,
+        });
+    }
+
+    events
+}
+
+/// Everything that happened in a saved game, as if it had been watched live
+pub fn record_events(record: &GameRecord) -> Result<Vec<GameEvent>, GameError> {
+    let mut game = record.initial_state()?;
+    let mut events = deal_events(&game);
+
+    for play in record.plays.iter() {
+        let play = game.can_play(play.cards().to_vec())?;
+        let seat = game.current_player;
+        let finished_before = game.finished().len();
+
+        game.play(play.clone());
+        events.extend(play_events(seat, &play, finished_before, &game));
+    }
+
+    Ok(events)
+}
@@ -0,0 +1,90 @@
+use serde::Serialize;
+
+use crate::card::Card;
+use crate::game::{GameRecord, GameState};
+use crate::play::Play;
+
+// turns a saved game into one html file that plays it back, for archiving games and sharing them.
+// web/replay.html is the viewer, and everything it shows is worked out here and put in the page as json,
+// so the page doesn't need anything else (no scripts or styles from anywhere, and no server)
+
+const VIEWER: &str = include_str!("../web/replay.html");
+
+// where the game goes in the viewer
+const PLACEHOLDER: &str = "/*GAME*/null";
+
+#[derive(Serialize)]
+struct Replay {
+    n_players: usize,
+    frames: Vec<Frame>,
+    placements: Vec<usize>,
+}
+
+// how everything looked right after a play (or the deal, for the first one).
+// cards are written the same way as everywhere else, like 3♣
+#[derive(Serialize)]
+struct Frame {
+    seat: Option<usize>, // who just played, None for the deal
+    description: String,
+    hands: Vec<Vec<String>>, // sorted
+    table: Option<String>,
+    table_owner: Option<usize>,
+    current_player: usize,
+    finished: Vec<usize>,
+}
+
+/// A self-contained html page that steps through `record`
+pub fn export_html(record: &GameRecord) -> Result<String, String> {
+    // replay shows us the game just before each play, which is just after the one before it
+    let mut frames = Vec::new();
+    let mut last = None;
+
+    let game = record
+        .replay(|game, play| {
+            frames.push(frame(game, last.take()));
+            last = Some((game.current_player, play.clone()));
+        })
+        .map_err(|e| format!("the record contains an illegal play: {:?}", e))?;
+    frames.push(frame(&game, last));
+
+    let replay = Replay {
+        n_players: record.rules.n_players,
+        frames,
+        placements: game.placements(),
+    };
+
+    // nothing inside a script tag can look like the end of it
+    let json = serde_json::to_string(&replay)
+        .map_err(|e| e.to_string())?
+        .replace("</", "<\\/");
+
+    Ok(VIEWER.replacen(PLACEHOLDER, &json, 1))
+}
+
+fn frame(game: &GameState, last: Option<(usize, Play)>) -> Frame {
+    let description = match &last {
+        None => "dealt".to_string(),
+        Some((seat, play)) if play.is_pass() => format!("seat {} passes", seat),
+        Some((seat, play)) => format!("seat {} plays {}", seat, play),
+    };
+
+    Frame {
+        seat: last.map(|(seat, _)| seat),
+        description,
+        hands: game.hands.iter().map(|hand| card_strings(hand)).collect(),
+        table: if game.have_control() {
+            None
+        } else {
+            game.get_play_on_table().map(|play| play.to_string())
+        },
+        table_owner: game.table_owner(),
+        current_player: game.current_player,
+        finished: game.finished().to_vec(),
+    }
+}
+
+fn card_strings(cards: &[Card]) -> Vec<String> {
+    let mut cards = cards.to_vec();
+    cards.sort();
+    cards.iter().map(|card| card.to_string()).collect()
+}
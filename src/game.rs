@@ -1,6 +1,14 @@
 use crate::play::Play;
+use crate::play::finder::Finder;
 
-use crate::card::{Card, THREE_OF_CLUBS};
+use crate::card::{entire_deck, Card, THREE_OF_CLUBS};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
 
 // MOTIVATION: if HumanPlayer or MachinePlayer had access to the regular GameState object,
 // they could call .hands and other info that would just be cheating. This struct only gives
@@ -26,6 +34,101 @@ impl<'a> SafeGameInterface<'a> {
     pub fn get_play_on_table(&self) -> Option<&Play> {
         self.inner.get_play_on_table()
     }
+
+    pub fn my_seat(&self) -> usize {
+        self.inner.current_player
+    }
+
+    pub fn have_control(&self) -> bool {
+        self.inner.have_control()
+    }
+
+    pub fn is_first_turn(&self) -> bool {
+        self.inner.is_first_turn()
+    }
+
+    pub fn legal_plays(&self) -> Vec<Play> {
+        self.inner.legal_plays()
+    }
+
+    pub fn n_players(&self) -> usize {
+        self.inner.n_players
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.inner.rules()
+    }
+
+    pub fn hand_sizes(&self) -> Vec<usize> {
+        // everyone can count how many cards the others are holding
+        self.inner.hands.iter().map(|h| h.len()).collect()
+    }
+
+    pub fn table_owner(&self) -> Option<usize> {
+        self.inner.table_owner()
+    }
+
+    pub fn history(&self) -> impl Iterator<Item = (usize, &Play)> {
+        self.inner.history()
+    }
+
+    pub fn is_finished(&self, player: usize) -> bool {
+        self.inner.is_finished(player)
+    }
+
+    pub fn finished(&self) -> &[usize] {
+        self.inner.finished()
+    }
+
+    pub fn played_cards(&self) -> Vec<Card> {
+        // everything that has been played is out in the open
+        self.inner.played_cards()
+    }
+
+    pub fn observation(&self) -> Observation {
+        self.inner.observation(self.my_seat())
+    }
+}
+
+/// Everything that `seat` is allowed to know about the game, in a form that can be sent around
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Observation {
+    pub seat: usize,
+    pub hand: Vec<Card>, // sorted
+    pub hand_sizes: Vec<usize>,
+    pub finished: Vec<usize>, // in the order that they went out
+    pub current_player: usize,
+    pub table: Option<Play>,
+    pub table_owner: Option<usize>,
+    pub have_control: bool, // whether the player to move can play anything
+    pub first_turn: bool,
+    pub history: Vec<(usize, Play)>,
+    pub legal_plays: Vec<Play>, // empty unless it is this seat's turn
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rules {
+    pub n_players: usize,
+    pub play_to_end: bool, // keep going after the first player goes out, so that everyone gets a place
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            n_players: 4,
+            play_to_end: false,
+        }
+    }
+}
+
+impl Rules {
+    pub fn check(&self) -> Result<(), String> {
+        if self.n_players < 2 || 52 % self.n_players != 0 {
+            return Err(format!("cannot deal the deck evenly to {} players", self.n_players));
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -36,13 +139,32 @@ pub struct GameState {
     turn_index: usize, // need to store because on first turn, must play a hand with three of clubs
     last_player_to_not_pass: usize,
     n_players: usize,
-    winning_player: Option<usize>,
+    rules: Rules,
+    finished: Vec<usize>, // the players who have gone out, in order
+    dealt: Vec<Vec<Card>>, // the hands everybody started with, so that the game can be replayed
     record: Vec<Play>, // all of the plays that happened in the game in order, for analysis
+    record_seats: Vec<usize>, // who made each of the plays in `record`
+    before: Vec<(Option<Play>, usize)>, // cards_down and last_player_to_not_pass before each play, for undo
+    undone: Vec<Play>, // what undo took back, most recent last, for redo
+    start: Option<Position>, // where the game was set up, if it didn't start from a deal
 }
 
 impl GameState {
     pub fn new(n_players: usize, cards: Vec<Card>) -> GameState {
-        let hands = deal(&cards, n_players);
+        let rules = Rules {
+            n_players,
+            ..Rules::default()
+        };
+
+        GameState::with_rules(rules, cards)
+    }
+
+    pub fn with_rules(rules: Rules, cards: Vec<Card>) -> GameState {
+        GameState::from_hands(rules, deal(&cards, rules.n_players))
+    }
+
+    pub fn from_hands(rules: Rules, hands: Vec<Vec<Card>>) -> GameState {
+        assert_eq!(hands.len(), rules.n_players);
 
         // figure out who has the three of clubs
         let mut player_who_starts = None;
@@ -55,17 +177,79 @@ impl GameState {
         let player_who_starts = player_who_starts.expect("Supplied deck didn't contain the 3♣");
 
         GameState {
+            dealt: hands.clone(),
             hands,
             current_player: player_who_starts,
             cards_down: None,
             turn_index: 0,
             last_player_to_not_pass: player_who_starts,
-            n_players,
-            winning_player: None,
+            n_players: rules.n_players,
+            rules,
+            finished: Vec::new(),
+            record: Vec::new(),
+            record_seats: Vec::new(),
+            before: Vec::new(),
+            undone: Vec::new(),
+            start: None,
+        }
+    }
+
+    pub fn from_position(position: &Position) -> Result<GameState, String> {
+        position.check()?;
+
+        let n_discarded = 52 - position.hands.iter().map(|h| h.len()).sum::<usize>();
+        let first_turn = n_discarded == 0;
+        let turn = position.turn()?;
+
+        let (cards_down, control) = match &position.table {
+            Some((owner, cards)) => {
+                let play = Play::infer_from_cards(cards.clone()).expect("checked by check");
+                (Some(play), position.control.unwrap_or(*owner))
+            }
+            None => (None, turn),
+        };
+
+        Ok(GameState {
+            dealt: position.hands.clone(),
+            hands: position.hands.clone(),
+            current_player: turn,
+            cards_down,
+            turn_index: if first_turn { 0 } else { 1 },
+            last_player_to_not_pass: control,
+            n_players: position.rules.n_players,
+            rules: position.rules,
+            finished: position.finished.clone(),
             record: Vec::new(),
+            record_seats: Vec::new(),
+            before: Vec::new(),
+            undone: Vec::new(),
+            start: Some(position.clone()),
+        })
+    }
+
+    /// Where things stand right now, which `from_position` turns back into a game
+    pub fn position(&self) -> Position {
+        Position {
+            rules: self.rules,
+            hands: self.hands.clone(),
+            discards: Some(self.played_cards()),
+            table: self.cards_down.clone().map(|play| {
+                let owner = self.table_owner().unwrap_or(self.last_player_to_not_pass);
+                (owner, play.into_cards())
+            }),
+            control: Some(self.last_player_to_not_pass),
+            turn: Some(self.current_player),
+            finished: self.finished.clone(),
         }
     }
 
+    pub fn give_control(&mut self, player: usize) {
+        // skips ahead to `player` leading with a clean table, for looking at a hand on its own
+        self.current_player = player;
+        self.last_player_to_not_pass = player;
+        self.turn_index = self.turn_index.max(1);
+    }
+
     pub fn can_play(&self, cards: Vec<Card>) -> Result<Play, GameError> {
         let play = Play::infer_from_cards(cards).ok_or(GameError::PlayDoesntExist)?;
 
@@ -97,9 +281,10 @@ impl GameState {
             }
         } // we don't have to list out the condition where we don't have control are passing, because this is always legal
 
-        // make sure we have all the cards in that play
+        // make sure we have all the cards in that play, and that none of them are used twice
+        let mut used = HashSet::new();
         for card in play.cards() {
-            if !self.my_hand().contains(card) {
+            if !self.my_hand().contains(card) || !used.insert(card) {
                 return Err(GameError::DontHaveCard);
             }
         }
@@ -107,15 +292,39 @@ impl GameState {
         Ok(play)
     }
 
+    pub fn legal_plays(&self) -> Vec<Play> {
+        // every play we could make right now, including a pass if we are allowed to pass.
+        // we go through can_play so that the kinds match what infer_from_cards would give
+        // (a strait that is also a flush only shows up once, as a strait flush)
+        let mut seen = HashSet::new();
+        let mut plays = Vec::new();
+
+        let candidates = Finder::new(self.my_hand().to_vec()).all_plays();
+
+        for candidate in candidates.into_iter().chain(std::iter::once(Play::pass())) {
+            if let Ok(play) = self.can_play(candidate.into_cards()) {
+                if seen.insert(play.clone()) {
+                    plays.push(play);
+                }
+            }
+        }
+
+        plays
+    }
+
     pub fn play(&mut self, play: Play) {
         // assumes that play is_legal
 
         subtract_cards(&mut self.hands[self.current_player], play.cards()).unwrap();
 
         self.record.push(play.clone());
+        self.record_seats.push(self.current_player);
+        self.before
+            .push((self.cards_down.clone(), self.last_player_to_not_pass));
+        self.undone.clear();
 
         if self.hands[self.current_player].is_empty() {
-            self.winning_player = Some(self.current_player);
+            self.finished.push(self.current_player);
         }
 
         if !play.is_pass() {
@@ -124,7 +333,128 @@ impl GameState {
         }
 
         self.turn_index += 1;
-        self.current_player = (self.current_player + 1) % self.n_players;
+        self.advance();
+    }
+
+    /// Takes back the last play, giving it back to whoever made it.
+    /// Returns `None` if nothing has been played since the deal (or since `from_position`)
+    pub fn undo(&mut self) -> Option<Play> {
+        let play = self.record.pop()?;
+        let seat = self.record_seats.pop().unwrap();
+        let (cards_down, last_player_to_not_pass) = self.before.pop().unwrap();
+
+        if self.hands[seat].is_empty() {
+            // that play was how they went out
+            self.finished.pop();
+        }
+        self.hands[seat].extend_from_slice(play.cards());
+
+        self.current_player = seat;
+        self.cards_down = cards_down;
+        self.last_player_to_not_pass = last_player_to_not_pass;
+        self.turn_index -= 1;
+
+        self.undone.push(play.clone());
+        Some(play)
+    }
+
+    /// Makes the last play that `undo` took back again, as long as nothing else has been played since
+    pub fn redo(&mut self) -> Option<Play> {
+        let play = self.undone.pop()?;
+
+        let undone = std::mem::take(&mut self.undone);
+        self.play(play.clone());
+        self.undone = undone;
+
+        Some(play)
+    }
+
+    fn advance(&mut self) {
+        // move on to the next player who still has cards.
+        // if we go past the player who made the last play, but they have gone out,
+        // then everybody else passed on it, and the next player gets control
+        let mut next = self.current_player;
+        let mut skipped_leader = false;
+
+        loop {
+            next = (next + 1) % self.n_players;
+
+            if !self.is_finished(next) || next == self.current_player {
+                break;
+            }
+
+            if next == self.last_player_to_not_pass {
+                skipped_leader = true;
+            }
+        }
+
+        if skipped_leader {
+            self.last_player_to_not_pass = next;
+        }
+
+        self.current_player = next;
+    }
+
+    pub fn is_finished(&self, player: usize) -> bool {
+        self.finished.contains(&player)
+    }
+
+    pub fn finished(&self) -> &[usize] {
+        // in the order that they went out
+        &self.finished
+    }
+
+    pub fn dealt(&self) -> &[Vec<Card>] {
+        &self.dealt
+    }
+
+    pub fn played_cards(&self) -> Vec<Card> {
+        // every card that isn't in somebody's hand, including the ones played before `from_position`
+        let mut cards = entire_deck();
+        cards.retain(|card| !self.hands.iter().any(|h| h.contains(card)));
+        cards
+    }
+
+    pub fn is_over(&self) -> bool {
+        if self.rules.play_to_end {
+            self.finished.len() + 1 >= self.n_players
+        } else {
+            !self.finished.is_empty()
+        }
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub fn observation(&self, seat: usize) -> Observation {
+        let mut hand = self.hands[seat].clone();
+        hand.sort();
+
+        let my_turn = seat == self.current_player && !self.is_over();
+
+        Observation {
+            seat,
+            hand,
+            hand_sizes: self.hands.iter().map(|h| h.len()).collect(),
+            finished: self.finished.clone(),
+            current_player: self.current_player,
+            table: self.cards_down.clone(),
+            table_owner: self.table_owner(),
+            have_control: self.have_control(),
+            first_turn: self.is_first_turn(),
+            history: self.history().map(|(s, p)| (s, p.clone())).collect(),
+            legal_plays: if my_turn { self.legal_plays() } else { Vec::new() },
+        }
+    }
+
+    pub fn to_record(&self) -> GameRecord {
+        GameRecord {
+            rules: self.rules,
+            hands: self.dealt.clone(),
+            plays: self.record.clone(),
+            start: self.start.clone(),
+        }
     }
 
     pub fn have_control(&self) -> bool {
@@ -135,8 +465,44 @@ impl GameState {
         &self.record
     }
 
+    pub fn history(&self) -> impl Iterator<Item = (usize, &Play)> {
+        self.record_seats.iter().cloned().zip(self.record.iter())
+    }
+
+    pub fn table_owner(&self) -> Option<usize> {
+        // who made the play that is on the table
+        self.history()
+            .filter(|(_, play)| !play.is_pass())
+            .last()
+            .map(|(seat, _)| seat)
+            .or_else(|| {
+                let start = self.start.as_ref()?;
+                start.table.as_ref().map(|&(owner, _)| owner)
+            })
+    }
+
     pub fn winning_player(&self) -> Option<usize> {
-        self.winning_player
+        self.finished.first().cloned()
+    }
+
+    pub fn placements(&self) -> Vec<usize> {
+        // where each player finished, 0 being first
+        // the players who went out come first, in the order that they did.
+        // everyone else is ranked by how many cards they have left (ties go to the lower seat)
+        let mut order = self.finished.clone();
+
+        let mut rest: Vec<usize> = (0..self.n_players)
+            .filter(|&i| !self.is_finished(i))
+            .collect();
+        rest.sort_by_key(|&i| (self.hands[i].len(), i));
+        order.extend(rest);
+
+        let mut placements = vec![0; self.n_players];
+        for (place, &player) in order.iter().enumerate() {
+            placements[player] = place;
+        }
+
+        placements
     }
 
     pub fn get_play_on_table(&self) -> Option<&Play> {
@@ -156,6 +522,238 @@ impl GameState {
     }
 }
 
+/// Everything needed to play a game over again
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub rules: Rules,
+    pub hands: Vec<Vec<Card>>,
+    pub plays: Vec<Play>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<Position>, // set when the game was set up part way through, then `hands` are its hands
+}
+
+impl GameRecord {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<GameRecord> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+
+    /// The game before anything in `plays` was played
+    pub fn initial_state(&self) -> Result<GameState, GameError> {
+        match &self.start {
+            Some(position) => GameState::from_position(position).map_err(GameError::BadPosition),
+            None => Ok(GameState::from_hands(self.rules, self.hands.clone())),
+        }
+    }
+
+    /// Plays the game back, calling `on_play` with the state each play was made in
+    pub fn replay(&self, mut on_play: impl FnMut(&GameState, &Play)) -> Result<GameState, GameError> {
+        let mut game = self.initial_state()?;
+
+        for play in self.plays.iter() {
+            let play = game.can_play(play.cards().to_vec())?;
+            on_play(&game, &play);
+            game.play(play);
+        }
+
+        Ok(game)
+    }
+}
+
+/// A game part of the way through, for puzzles and for asking what would have happened.
+/// Set one up with `new` and the `with_` methods, then start it with `GameState::from_position`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Position {
+    rules: Rules,
+    hands: Vec<Vec<Card>>,
+    discards: Option<Vec<Card>>, // everything not in a hand when this isn't given
+    table: Option<(usize, Vec<Card>)>, // who played it, and what
+    control: Option<usize>,      // whoever made the play on the table unless given
+    turn: Option<usize>,
+    finished: Vec<usize>, // in the order that they went out
+}
+
+impl Position {
+    pub fn new(rules: Rules) -> Position {
+        Position {
+            rules,
+            hands: vec![Vec::new(); rules.n_players],
+            discards: None,
+            table: None,
+            control: None,
+            turn: None,
+            finished: Vec::new(),
+        }
+    }
+
+    pub fn with_hand(mut self, seat: usize, cards: Vec<Card>) -> Position {
+        if seat >= self.hands.len() {
+            self.hands.resize(seat + 1, Vec::new());
+        }
+        self.hands[seat] = cards;
+        self
+    }
+
+    /// The cards that have already been played, including the ones on the table
+    pub fn with_discards(mut self, cards: Vec<Card>) -> Position {
+        self.discards = Some(cards);
+        self
+    }
+
+    /// `cards` are on the table, played by `owner`
+    pub fn with_table(mut self, owner: usize, cards: Vec<Card>) -> Position {
+        self.table = Some((owner, cards));
+        self
+    }
+
+    /// Who gets to play anything they want once the turn comes around to them,
+    /// when that isn't whoever made the play on the table (because they went out after making it)
+    pub fn with_control(mut self, seat: usize) -> Position {
+        self.control = Some(seat);
+        self
+    }
+
+    pub fn with_turn(mut self, seat: usize) -> Position {
+        self.turn = Some(seat);
+        self
+    }
+
+    pub fn with_finished(mut self, seats: Vec<usize>) -> Position {
+        self.finished = seats;
+        self
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub fn hands(&self) -> &[Vec<Card>] {
+        &self.hands
+    }
+
+    /// Whose turn it is. When nothing has been played it is whoever has the 3♣
+    pub fn turn(&self) -> Result<usize, String> {
+        match self.turn {
+            Some(seat) => Ok(seat),
+            None => self
+                .hands
+                .iter()
+                .position(|h| h.contains(&THREE_OF_CLUBS))
+                .filter(|_| self.hands.iter().map(|h| h.len()).sum::<usize>() == 52)
+                .ok_or_else(|| "whose turn it is wasn't given".to_string()),
+        }
+    }
+
+    /// Makes sure that this could come up in a real game
+    pub fn check(&self) -> Result<(), String> {
+        self.rules.check()?;
+        let n = self.rules.n_players;
+
+        if self.hands.len() != n {
+            return Err(format!("{} hands for {} players", self.hands.len(), n));
+        }
+
+        // every card shows up once, somewhere
+        let mut seen = HashSet::new();
+        let discards = self.discards.iter().flatten();
+        for card in self.hands.iter().flatten().chain(discards) {
+            if !seen.insert(*card) {
+                return Err(format!("{} shows up more than once", card));
+            }
+        }
+        let n_held = self.hands.iter().map(|h| h.len()).sum::<usize>();
+        if let Some(discards) = &self.discards {
+            if n_held + discards.len() != 52 {
+                return Err(format!(
+                    "{} cards in hands and {} discarded, instead of 52",
+                    n_held,
+                    discards.len()
+                ));
+            }
+        }
+
+        let turn = self.turn()?;
+        if turn >= n {
+            return Err(format!("no seat {}", turn));
+        }
+
+        let mut out = HashSet::new();
+        for &seat in self.finished.iter() {
+            if seat >= n || !out.insert(seat) {
+                return Err(format!("seat {} can't have gone out there", seat));
+            }
+        }
+        for (seat, hand) in self.hands.iter().enumerate() {
+            if hand.is_empty() != out.contains(&seat) {
+                return Err(format!(
+                    "seat {} has to be listed as out exactly when their hand is empty",
+                    seat
+                ));
+            }
+        }
+        if out.contains(&turn) {
+            return Err(format!(
+                "it can't be seat {}'s turn, they have gone out",
+                turn
+            ));
+        }
+
+        if n_held == 52 {
+            // nobody has played yet, so this is the first turn
+            if self.table.is_some() {
+                return Err(
+                    "there can't be anything on the table before the first play".to_string()
+                );
+            }
+            if !self.hands[turn].contains(&THREE_OF_CLUBS) {
+                return Err("the first play has to be made by whoever has the 3♣".to_string());
+            }
+        }
+
+        match &self.table {
+            Some((owner, cards)) => {
+                if *owner >= n {
+                    return Err(format!("no seat {}", owner));
+                }
+                if cards.is_empty() || Play::infer_from_cards(cards.clone()).is_none() {
+                    return Err(
+                        "what's on the table isn't something that can be played".to_string()
+                    );
+                }
+                for card in cards {
+                    let discarded = match &self.discards {
+                        Some(discards) => discards.contains(card),
+                        None => !self.hands.iter().any(|h| h.contains(card)),
+                    };
+                    if !discarded {
+                        return Err(format!("{} is on the table and in a hand", card));
+                    }
+                }
+            }
+            None => {
+                if self.control.is_some_and(|seat| seat != turn) {
+                    return Err(
+                        "with nothing on the table, whoever's turn it is has control".to_string(),
+                    );
+                }
+            }
+        }
+
+        if let Some(control) = self.control {
+            if control >= n {
+                return Err(format!("seat {} can't have control", control));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub enum GameError {
     DontHaveCard,
@@ -164,6 +762,7 @@ pub enum GameError {
     WrongLength,
     CannotPass,
     PlayDoesntExist,
+    BadPosition(String),
 }
 
 fn subtract_cards(cards: &mut Vec<Card>, to_remove: &[Card]) -> Option<()> {
@@ -197,3 +796,133 @@ pub fn deal(cards: &[Card], n_groups: usize) -> Vec<Vec<Card>> {
 
     groups
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::parse_cards;
+
+    struct Endgame {
+        hands: [&'static str; 4],
+        table: Option<(usize, &'static str)>, // who played it, and what
+        turn: usize,
+    }
+
+    // the ends of real games, with everything that isn't in a hand already played
+    const ENDGAMES: [Endgame; 4] = [
+        Endgame {
+            hands: ["3C 4D 9H", "5S 5H KD", "7C", "AS 2H"],
+            table: None,
+            turn: 0,
+        },
+        Endgame {
+            hands: ["TC TD", "JS", "", "6H 6S 2S"],
+            table: Some((1, "8C")),
+            turn: 3,
+        },
+        Endgame {
+            hands: ["3D 4D 5D 7D 9D QS", "8S 8H 8D KC KS", "4C 5C 6C 7H 8C", "JH"],
+            table: Some((3, "3S 4S 5S 6S 7S")),
+            turn: 0,
+        },
+        Endgame {
+            hands: ["", "9C 9S", "QC QD 2D", "AH"],
+            table: Some((2, "JC JD")),
+            turn: 3,
+        },
+    ];
+
+    fn position(endgame: &Endgame) -> Position {
+        let mut position = Position::new(Rules::default()).with_turn(endgame.turn);
+        for (seat, hand) in endgame.hands.iter().enumerate() {
+            position = position.with_hand(seat, parse_cards(hand).unwrap());
+        }
+        if let Some((owner, cards)) = endgame.table {
+            position = position.with_table(owner, parse_cards(cards).unwrap());
+        }
+        let finished = (0..4).filter(|&s| endgame.hands[s].is_empty()).collect();
+        position.with_finished(finished)
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Snapshot {
+        hands: Vec<Vec<Card>>, // sorted, since undo gives the cards back at the end
+        turn: usize,
+        table: Option<Play>,
+        control: bool,
+        finished: Vec<usize>,
+    }
+
+    fn snapshot(game: &GameState) -> Snapshot {
+        let mut hands = game.hands.clone();
+        for hand in hands.iter_mut() {
+            hand.sort();
+        }
+
+        Snapshot {
+            hands,
+            turn: game.current_player,
+            table: game.get_play_on_table().cloned(),
+            control: game.have_control(),
+            finished: game.finished().to_vec(),
+        }
+    }
+
+    #[test]
+    fn positions_come_back_the_same() {
+        for endgame in ENDGAMES.iter() {
+            let game = GameState::from_position(&position(endgame)).unwrap();
+            let again = GameState::from_position(&game.position()).unwrap();
+            assert_eq!(snapshot(&game), snapshot(&again), "{:?}", endgame.hands);
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_retrace_the_game() {
+        for endgame in ENDGAMES.iter() {
+            let mut game = GameState::from_position(&position(endgame)).unwrap();
+
+            let mut seen = vec![snapshot(&game)];
+            while !game.is_over() {
+                let play = game.legal_plays().remove(0);
+                game.play(play);
+                seen.push(snapshot(&game));
+
+                // the position of the game so far starts the same game
+                let from = GameState::from_position(&game.position()).unwrap();
+                assert_eq!(snapshot(&from), snapshot(&game));
+            }
+
+            for before in seen.iter().rev().skip(1) {
+                assert!(game.undo().is_some());
+                assert_eq!(&snapshot(&game), before);
+            }
+            assert!(game.undo().is_none());
+
+            for after in seen.iter().skip(1) {
+                assert!(game.redo().is_some());
+                assert_eq!(&snapshot(&game), after);
+            }
+            assert!(game.redo().is_none());
+        }
+    }
+
+    #[test]
+    fn check_turns_down_what_cant_happen() {
+        let first = position(&ENDGAMES[0]);
+        let doubled = first.clone().with_hand(2, parse_cards("7C 3C").unwrap());
+        assert!(doubled.check().unwrap_err().contains("more than once"));
+
+        let on_table = first.clone().with_table(1, parse_cards("KD").unwrap());
+        assert!(on_table.check().unwrap_err().contains("in a hand"));
+
+        let out = position(&ENDGAMES[1]).with_turn(2);
+        assert!(out.check().unwrap_err().contains("gone out"));
+
+        let unlisted = position(&ENDGAMES[3]).with_finished(Vec::new());
+        assert!(unlisted.check().unwrap_err().contains("listed as out"));
+
+        let missing = first.with_discards(Vec::new());
+        assert!(missing.check().unwrap_err().contains("instead of 52"));
+    }
+}
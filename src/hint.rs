@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+use crate::bot::MachinePlayer;
+use crate::card::{Card, Rank};
+use crate::control::{ControlTable, Situation};
+use crate::game::SafeGameInterface;
+use crate::partition::{best_partitions, Objective, MAX_CARDS};
+use crate::play::{Play, PlayKind};
+use crate::train::DEFAULT_PARAMETERS;
+
+// help for people who are still learning: what the machine bot would play, a few other plays worth
+// thinking about, and a few words on each. and before a play is made, whether it breaks up something
+// better. "better" comes from splitting the hand into as few plays as it can go (`partition`)
+
+// how many other plays to suggest
+const ALTERNATIVES: usize = 3;
+
+// how many reasons to give for each play
+const REASONS: usize = 3;
+
+// cards below this rank are low
+const LOW: Rank = Rank::Nine;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Hint {
+    pub suggested: Suggestion,
+    pub alternatives: Vec<Suggestion>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub play: Play,
+    pub reasons: Vec<String>,
+}
+
+/// What the machine bot would do, and what else there is, for the player to move
+pub fn hint(game: &SafeGameInterface) -> Hint {
+    hint_with(&MachinePlayer::new(DEFAULT_PARAMETERS), game)
+}
+
+pub fn hint_with(player: &MachinePlayer, game: &SafeGameInterface) -> Hint {
+    let (chosen, report) = player.choose_play_with_report(game);
+    let split = best_split(game.my_hand());
+
+    let mut alternatives: Vec<Suggestion> = report
+        .candidates
+        .iter()
+        .map(|c| &c.play)
+        .filter(|&play| *play != chosen && game.can_play(play.cards().to_vec()).is_ok())
+        .take(ALTERNATIVES)
+        .map(|play| suggest(game, &split, play))
+        .collect();
+
+    if !chosen.is_pass() && game.can_play(vec![]).is_ok() {
+        alternatives.push(suggest(game, &split, &Play::pass()));
+    }
+
+    let mut suggested = suggest(game, &split, &chosen);
+    suggested
+        .reasons
+        .insert(0, "what the bot would play".to_string());
+    suggested.reasons.truncate(REASONS);
+
+    Hint {
+        suggested,
+        alternatives,
+    }
+}
+
+/// Anything to think twice about before making `play`
+pub fn warnings(game: &SafeGameInterface, play: &Play) -> Vec<String> {
+    if play.is_pass() {
+        return Vec::new();
+    }
+
+    let hand = game.my_hand();
+    let before = best_split(hand);
+    let rest: Vec<Card> = hand
+        .iter()
+        .filter(|c| !play.cards().contains(c))
+        .cloned()
+        .collect();
+    let after = best_split(&rest);
+
+    // the play is one of the plays, so the rest should take one fewer
+    if after.len() < before.len() {
+        return Vec::new();
+    }
+
+    let broken: Vec<String> = before
+        .iter()
+        .filter(|p| p.cards().len() > 1)
+        .filter(|p| {
+            let shared = p
+                .cards()
+                .iter()
+                .filter(|c| play.cards().contains(c))
+                .count();
+            shared > 0 && shared < p.cards().len()
+        })
+        .map(|p| format!("breaks up your {} ({})", kind_name(p.kind()), p))
+        .collect();
+
+    let mut warnings = broken;
+    warnings.push(format!(
+        "the rest of your hand takes {} plays after this, it could have been {}",
+        after.len(),
+        before.len() - 1
+    ));
+    warnings
+}
+
+// a hand too big to split up all at once (with fewer players) is split up a piece at a time,
+// lowest cards first, which is close enough to say something useful
+fn best_split(hand: &[Card]) -> Vec<Play> {
+    let mut cards = hand.to_vec();
+    cards.sort();
+    cards
+        .chunks(MAX_CARDS)
+        .flat_map(|chunk| {
+            best_partitions(chunk, 1, Objective::FewestPlays)
+                .expect("no more than MAX_CARDS")
+                .remove(0)
+                .plays
+        })
+        .collect()
+}
+
+fn suggest(game: &SafeGameInterface, split: &[Play], play: &Play) -> Suggestion {
+    let mut reasons = Vec::new();
+
+    if play.is_pass() {
+        reasons.push("saves your cards for later".to_string());
+        return Suggestion {
+            play: play.clone(),
+            reasons,
+        };
+    }
+
+    if play.cards().len() == game.my_hand().len() {
+        reasons.push("goes out".to_string());
+    }
+
+    let control = ControlTable::built_in().probability(play, Situation::of(game));
+    if control >= 0.5 {
+        reasons.push("likely wins control".to_string());
+    }
+
+    if split.iter().any(|p| same_cards(p, play)) {
+        reasons.push("fits how your hand splits up".to_string());
+    }
+
+    // the best combination that this leaves alone, when something else we could play wouldn't
+    let others: Vec<Play> = game
+        .legal_plays()
+        .into_iter()
+        .filter(|p| !p.is_pass() && !same_cards(p, play))
+        .collect();
+    let kept = split
+        .iter()
+        .filter(|p| p.cards().len() > 1 && !overlaps(p, play))
+        .filter(|p| others.iter().any(|other| overlaps(p, other)))
+        .max();
+    if let Some(kept) = kept {
+        reasons.push(format!("keeps your {} intact", kind_name(kept.kind())));
+    }
+
+    let low = play.ranking_card().unwrap().rank < LOW;
+    if low && play.cards().len() <= 2 {
+        reasons.push("gets rid of low cards".to_string());
+    }
+    if play.cards().len() == 5 {
+        reasons.push("gets rid of five cards at once".to_string());
+    }
+
+    reasons.truncate(REASONS);
+    Suggestion {
+        play: play.clone(),
+        reasons,
+    }
+}
+
+fn same_cards(a: &Play, b: &Play) -> bool {
+    a.cards().len() == b.cards().len() && a.cards().iter().all(|c| b.cards().contains(c))
+}
+
+fn overlaps(a: &Play, b: &Play) -> bool {
+    a.cards().iter().any(|c| b.cards().contains(c))
+}
+
+pub fn kind_name(kind: PlayKind) -> &'static str {
+    match kind {
+        PlayKind::Pass => "pass",
+        PlayKind::Single => "single",
+        PlayKind::Pair => "pair",
+        PlayKind::Strait => "strait",
+        PlayKind::Flush => "flush",
+        PlayKind::FullHouse => "full house",
+        PlayKind::FourOfAKind => "four of a kind",
+        PlayKind::StraitFlush => "strait flush",
+    }
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.play)?;
+        if !self.reasons.is_empty() {
+            write!(f, ": {}", self.reasons.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "try {}", self.suggested)?;
+        for alternative in self.alternatives.iter() {
+            writeln!(f, "  or {}", alternative)?;
+        }
+        Ok(())
+    }
+}
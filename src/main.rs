@@ -2,26 +2,74 @@
 
 mod bot;
 
+mod control;
+
+mod report;
+
 mod card;
 
 mod play;
 
 mod game;
 
+mod events;
+
+mod runner;
+
+mod mcts;
+
+mod analysis;
+
+mod review;
+
+mod export;
+
+mod stats;
+
+mod threat;
+
+mod opponent;
+
+mod strength;
+
+mod partition;
+
+mod hint;
+
+mod personality;
+
 mod util;
 
+mod neural;
+
+mod reinforce;
+
 mod train;
-use train::{DEFAULT_PARAMETERS, training_step};
 
-fn main() {
-    let mut current_parameters = DEFAULT_PARAMETERS;
+mod tui;
+
+mod protocol;
+
+mod server;
+
+mod websocket;
 
-    for _ in 0.. {
-        let results = training_step(current_parameters);
+mod engine;
 
-        println!("{:?}", results);
+mod client;
 
-        current_parameters = results.0;
+mod registry;
+
+mod cli;
+
+use std::env;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if let Err(e) = cli::run(args) {
+        eprintln!("{}", e);
+        process::exit(1);
     }
 }
-
@@ -0,0 +1,199 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use crate::bot::Player;
+use crate::card::{entire_deck, Card};
+use crate::game::{GameState, Position, SafeGameInterface};
+use crate::play::Play;
+
+// monte carlo search over the plays we could make right now.
+// we can't see the other hands, so every playout first deals the cards we haven't seen
+// to the other players at random (keeping how many each of them holds), then makes our play
+// and finishes the game with everybody playing at random.
+// which play gets the next playout is picked with ucb1, so the promising ones get looked at the most
+
+pub const DEFAULT_ITERATIONS: usize = 1000;
+
+// how much ucb1 favours plays that haven't been tried much
+const EXPLORATION: f64 = 1.4;
+
+pub struct MctsPlayer {
+    iterations: usize,
+    time_limit: Option<Duration>,
+    rng: RefCell<StdRng>,
+}
+
+impl MctsPlayer {
+    pub fn new(iterations: usize, time_limit: Option<Duration>, seed: u64) -> MctsPlayer {
+        MctsPlayer {
+            iterations,
+            time_limit,
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl Player for MctsPlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        let stats = search(
+            game,
+            self.iterations,
+            self.time_limit,
+            &mut *self.rng.borrow_mut(),
+        );
+
+        stats
+            .into_iter()
+            .max_by_key(|s| s.visits)
+            .map(|s| s.play)
+            .expect("there is always a legal play")
+    }
+}
+
+/// How the playouts that started with `play` went
+#[derive(Clone, Debug)]
+pub struct PlayStats {
+    pub play: Play,
+    pub visits: usize,
+    pub total_reward: f64, // 1 for going out first, 0 for last
+    pub wins: usize,
+}
+
+impl PlayStats {
+    pub fn mean_reward(&self) -> f64 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.total_reward / self.visits as f64
+        }
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.visits as f64
+        }
+    }
+}
+
+/// Runs up to `iterations` playouts (fewer if `time_limit` runs out first) for the player to move
+pub fn search(
+    game: &SafeGameInterface,
+    iterations: usize,
+    time_limit: Option<Duration>,
+    rng: &mut impl Rng,
+) -> Vec<PlayStats> {
+    let deadline = time_limit.map(|t| Instant::now() + t);
+    let me = game.my_seat();
+
+    let mut stats: Vec<PlayStats> = game
+        .legal_plays()
+        .into_iter()
+        .map(|play| PlayStats {
+            play,
+            visits: 0,
+            total_reward: 0.0,
+            wins: 0,
+        })
+        .collect();
+
+    // with only one thing to do, there is nothing to find out
+    if stats.len() == 1 {
+        return stats;
+    }
+
+    for iteration in 0..iterations {
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            break;
+        }
+
+        let choice = ucb1(&stats, iteration);
+
+        let mut playout = determinize(game, rng);
+        playout.play(stats[choice].play.clone());
+        finish_randomly(&mut playout, rng);
+
+        stats[choice].visits += 1;
+        stats[choice].total_reward += reward(&playout, me);
+        if playout.winning_player() == Some(me) {
+            stats[choice].wins += 1;
+        }
+    }
+
+    stats
+}
+
+fn ucb1(stats: &[PlayStats], iteration: usize) -> usize {
+    if let Some(untried) = stats.iter().position(|s| s.visits == 0) {
+        return untried;
+    }
+
+    let log_total = (iteration as f64).ln();
+
+    (0..stats.len())
+        .max_by(|&a, &b| {
+            let score = |s: &PlayStats| {
+                s.mean_reward() + EXPLORATION * (log_total / s.visits as f64).sqrt()
+            };
+            score(&stats[a]).total_cmp(&score(&stats[b]))
+        })
+        .unwrap()
+}
+
+/// A whole game that looks the same as `game` from where the player to move is sitting,
+/// with the cards they can't see dealt out at random
+pub fn determinize(game: &SafeGameInterface, rng: &mut impl Rng) -> GameState {
+    let me = game.my_seat();
+    let played = game.played_cards();
+
+    let mut unseen: Vec<Card> = entire_deck()
+        .into_iter()
+        .filter(|card| !game.my_hand().contains(card) && !played.contains(card))
+        .collect();
+    unseen.shuffle(rng);
+
+    let mut position = Position::new(game.rules())
+        .with_discards(played)
+        .with_turn(me)
+        .with_finished(game.finished().to_vec());
+
+    for (seat, &size) in game.hand_sizes().iter().enumerate() {
+        let hand = if seat == me {
+            game.my_hand().to_vec()
+        } else {
+            unseen.split_off(unseen.len() - size)
+        };
+        position = position.with_hand(seat, hand);
+    }
+
+    if let (Some(table), Some(owner)) = (game.get_play_on_table(), game.table_owner()) {
+        position = position.with_table(owner, table.cards().to_vec());
+        if game.have_control() {
+            // the owner went out, and everybody passed on it
+            position = position.with_control(me);
+        }
+    }
+
+    GameState::from_position(&position).expect("everything we can see is consistent")
+}
+
+fn finish_randomly(game: &mut GameState, rng: &mut impl Rng) {
+    while !game.is_over() {
+        let plays = game.legal_plays();
+        let play = plays
+            .choose(rng)
+            .expect("there is always a legal play")
+            .clone();
+        game.play(play);
+    }
+}
+
+fn reward(game: &GameState, seat: usize) -> f64 {
+    let last = (game.rules().n_players - 1) as f64;
+    1.0 - game.placements()[seat] as f64 / last
+}
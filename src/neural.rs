@@ -0,0 +1,506 @@
+use ordered_float::OrderedFloat;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use crate::bot::Player;
+use crate::card::{entire_deck, Card};
+use crate::game::{GameState, Rules, SafeGameInterface};
+use crate::play::finder::Finder;
+use crate::play::{Play, PlayKind};
+use crate::train::play_game_on;
+
+// a small multi-layer perceptron that scores each legal play, as a learned alternative to `bot::cost`.
+// the network maps the features of one play to a single number, and a softmax over the scores
+// of every legal play gives a probability distribution over what to do next
+
+pub const N_FEATURES: usize = 25;
+
+const ALL_KINDS: [PlayKind; 8] = [
+    PlayKind::Pass,
+    PlayKind::Single,
+    PlayKind::Pair,
+    PlayKind::Strait,
+    PlayKind::Flush,
+    PlayKind::FullHouse,
+    PlayKind::FourOfAKind,
+    PlayKind::StraitFlush,
+];
+
+/// Every legal play for the player to move, along with the features the network sees for it
+pub fn legal_play_features(game: &SafeGameInterface) -> Vec<(Play, Vec<f64>)> {
+    let hand_plays = Finder::new(game.my_hand().to_vec()).all_plays();
+
+    game.legal_plays()
+        .into_iter()
+        .map(|play| {
+            let features = features(game, &hand_plays, &play);
+            (play, features)
+        })
+        .collect()
+}
+
+fn features(game: &SafeGameInterface, hand_plays: &[Play], play: &Play) -> Vec<f64> {
+    let hand = game.my_hand();
+    let mut features = Vec::with_capacity(N_FEATURES);
+
+    // what we are playing
+    push_kind(&mut features, Some(play.kind()));
+    features.push(play.ranking_card().map_or(0.0, |c| c.numeric_value() as f64 / 51.0));
+    features.push(play.cards().len() as f64 / 5.0);
+
+    // where it leaves us
+    features.push(hand.len() as f64 / 13.0);
+    features.push((hand.len() - play.cards().len()) as f64 / 13.0);
+    features.push(if game.have_control() { 1.0 } else { 0.0 });
+
+    // what we are playing on
+    let table = game.get_play_on_table();
+    push_kind(&mut features, table.map(|t| t.kind()));
+    features.push(
+        table
+            .and_then(|t| t.ranking_card())
+            .map_or(0.0, |c| c.numeric_value() as f64 / 51.0),
+    );
+
+    // how many of our other options this play uses up
+    let broken = hand_plays
+        .iter()
+        .filter(|p| p.cards().iter().any(|c| play.cards().contains(c)))
+        .count();
+    features.push(broken as f64 / hand_plays.len().max(1) as f64);
+
+    // are we spending our best cards
+    let high_cards = play
+        .cards()
+        .iter()
+        .filter(|c| c.rank >= crate::card::Rank::Ace)
+        .count();
+    features.push(high_cards as f64 / 5.0);
+
+    // the worst card we are stuck with afterwards
+    let lowest_left = hand
+        .iter()
+        .filter(|c| !play.cards().contains(c))
+        .map(|c| c.numeric_value())
+        .min();
+    features.push(lowest_left.map_or(1.0, |v| v as f64 / 51.0));
+
+    debug_assert_eq!(features.len(), N_FEATURES);
+    features
+}
+
+fn push_kind(features: &mut Vec<f64>, kind: Option<PlayKind>) {
+    features.extend(
+        ALL_KINDS
+            .iter()
+            .map(|&k| if Some(k) == kind { 1.0 } else { 0.0 }),
+    );
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Dense {
+    n_inputs: usize,
+    n_outputs: usize,
+    weights: Vec<f64>, // row major, one row per output
+    biases: Vec<f64>,
+}
+
+impl Dense {
+    fn new(n_inputs: usize, n_outputs: usize, rng: &mut impl Rng) -> Dense {
+        // he initialization, since everything but the last layer goes through a relu
+        let bound = (6.0 / n_inputs as f64).sqrt();
+
+        Dense {
+            n_inputs,
+            n_outputs,
+            weights: (0..n_inputs * n_outputs)
+                .map(|_| rng.gen_range(-bound, bound))
+                .collect(),
+            biases: vec![0.0; n_outputs],
+        }
+    }
+
+    fn zeros_like(&self) -> Dense {
+        Dense {
+            n_inputs: self.n_inputs,
+            n_outputs: self.n_outputs,
+            weights: vec![0.0; self.weights.len()],
+            biases: vec![0.0; self.biases.len()],
+        }
+    }
+
+    fn forward(&self, input: &[f64], relu: bool) -> Vec<f64> {
+        (0..self.n_outputs)
+            .map(|o| {
+                let row = &self.weights[o * self.n_inputs..(o + 1) * self.n_inputs];
+                let z = self.biases[o] + row.iter().zip(input).map(|(w, x)| w * x).sum::<f64>();
+
+                if relu {
+                    z.max(0.0)
+                } else {
+                    z
+                }
+            })
+            .collect()
+    }
+}
+
+/// A dense network with relu hidden layers and a single linear output
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Network {
+    layers: Vec<Dense>,
+}
+
+impl Network {
+    pub fn new(hidden_sizes: &[usize], seed: u64) -> Network {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let mut sizes = vec![N_FEATURES];
+        sizes.extend_from_slice(hidden_sizes);
+        sizes.push(1);
+
+        let layers = sizes
+            .windows(2)
+            .map(|w| Dense::new(w[0], w[1], &mut rng))
+            .collect();
+
+        Network { layers }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Network> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        Ok(serde_json::to_writer(writer, self)?)
+    }
+
+    fn zeros_like(&self) -> Network {
+        Network {
+            layers: self.layers.iter().map(Dense::zeros_like).collect(),
+        }
+    }
+
+    fn parameters(&self) -> impl Iterator<Item = &f64> {
+        self.layers
+            .iter()
+            .flat_map(|l| l.weights.iter().chain(l.biases.iter()))
+    }
+
+    fn parameters_mut(&mut self) -> impl Iterator<Item = &mut f64> {
+        self.layers
+            .iter_mut()
+            .flat_map(|l| l.weights.iter_mut().chain(l.biases.iter_mut()))
+    }
+
+    fn n_parameters(&self) -> usize {
+        self.parameters().count()
+    }
+
+    pub fn score(&self, features: &[f64]) -> f64 {
+        self.activations(features).last().unwrap()[0]
+    }
+
+    fn activations(&self, features: &[f64]) -> Vec<Vec<f64>> {
+        // the input, followed by the output of every layer
+        let mut activations = vec![features.to_vec()];
+        let last = self.layers.len() - 1;
+
+        for (i, layer) in self.layers.iter().enumerate() {
+            let next = layer.forward(activations.last().unwrap(), i != last);
+            activations.push(next);
+        }
+
+        activations
+    }
+
+    fn backward(&self, activations: &[Vec<f64>], d_output: f64, gradient: &mut Network) {
+        // adds d(loss)/d(parameters) to gradient, given d(loss)/d(output)
+        let mut delta = vec![d_output];
+
+        for i in (0..self.layers.len()).rev() {
+            let layer = &self.layers[i];
+            let grad_layer = &mut gradient.layers[i];
+            let input = &activations[i];
+
+            let mut next_delta = vec![0.0; layer.n_inputs];
+
+            for (o, &d) in delta.iter().enumerate() {
+                grad_layer.biases[o] += d;
+
+                for j in 0..layer.n_inputs {
+                    grad_layer.weights[o * layer.n_inputs + j] += d * input[j];
+                    next_delta[j] += d * layer.weights[o * layer.n_inputs + j];
+                }
+            }
+
+            // the input to this layer went through a relu, unless it is the raw features
+            if i > 0 {
+                for (d, &a) in next_delta.iter_mut().zip(input.iter()) {
+                    if a <= 0.0 {
+                        *d = 0.0;
+                    }
+                }
+            }
+
+            delta = next_delta;
+        }
+    }
+
+    /// The probability of choosing each candidate, given their features
+    pub fn policy(&self, candidates: &[Vec<f64>]) -> Vec<f64> {
+        self.policy_with_temperature(candidates, 1.0)
+    }
+
+    pub fn policy_with_temperature(&self, candidates: &[Vec<f64>], temperature: f64) -> Vec<f64> {
+        let scores: Vec<f64> = candidates
+            .iter()
+            .map(|c| self.score(c) / temperature)
+            .collect();
+        softmax(&scores)
+    }
+
+    fn accumulate_gradient(&self, decision: &Decision, gradient: &mut Network) -> f64 {
+        // weighted cross entropy between the softmax over candidates and the play that was chosen.
+        // returns the loss, and adds its gradient into `gradient`
+        let activations: Vec<Vec<Vec<f64>>> = decision
+            .candidates
+            .iter()
+            .map(|c| self.activations(c))
+            .collect();
+
+        let scores: Vec<f64> = activations.iter().map(|a| a.last().unwrap()[0]).collect();
+        let probabilities = softmax(&scores);
+
+        for (i, (activation, &p)) in activations.iter().zip(probabilities.iter()).enumerate() {
+            let target = if i == decision.chosen { 1.0 } else { 0.0 };
+            self.backward(activation, decision.weight * (p - target), gradient);
+        }
+
+        -decision.weight * probabilities[decision.chosen].max(1e-12).ln()
+    }
+}
+
+pub fn softmax(scores: &[f64]) -> Vec<f64> {
+    let max = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let exps: Vec<f64> = scores.iter().map(|s| (s - max).exp()).collect();
+    let total: f64 = exps.iter().sum();
+
+    exps.into_iter().map(|e| e / total).collect()
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum OptimizerKind {
+    Sgd,
+    Adam { beta1: f64, beta2: f64 },
+}
+
+pub struct Optimizer {
+    kind: OptimizerKind,
+    learning_rate: f64,
+    step: i32,
+    first_moment: Vec<f64>,
+    second_moment: Vec<f64>,
+}
+
+impl Optimizer {
+    pub fn sgd(learning_rate: f64) -> Optimizer {
+        Optimizer::new(OptimizerKind::Sgd, learning_rate)
+    }
+
+    pub fn adam(learning_rate: f64) -> Optimizer {
+        Optimizer::new(
+            OptimizerKind::Adam {
+                beta1: 0.9,
+                beta2: 0.999,
+            },
+            learning_rate,
+        )
+    }
+
+    pub fn new(kind: OptimizerKind, learning_rate: f64) -> Optimizer {
+        Optimizer {
+            kind,
+            learning_rate,
+            step: 0,
+            first_moment: Vec::new(),
+            second_moment: Vec::new(),
+        }
+    }
+
+    fn apply(&mut self, network: &mut Network, gradient: &Network) {
+        self.step += 1;
+
+        match self.kind {
+            OptimizerKind::Sgd => {
+                for (p, g) in network.parameters_mut().zip(gradient.parameters()) {
+                    *p -= self.learning_rate * g;
+                }
+            }
+            OptimizerKind::Adam { beta1, beta2 } => {
+                if self.first_moment.is_empty() {
+                    self.first_moment = vec![0.0; network.n_parameters()];
+                    self.second_moment = vec![0.0; network.n_parameters()];
+                }
+
+                let correction1 = 1.0 - beta1.powi(self.step);
+                let correction2 = 1.0 - beta2.powi(self.step);
+
+                let moments = self.first_moment.iter_mut().zip(self.second_moment.iter_mut());
+
+                for ((p, g), (m, v)) in network
+                    .parameters_mut()
+                    .zip(gradient.parameters())
+                    .zip(moments)
+                {
+                    *m = beta1 * *m + (1.0 - beta1) * g;
+                    *v = beta2 * *v + (1.0 - beta2) * g * g;
+
+                    let m_hat = *m / correction1;
+                    let v_hat = *v / correction2;
+
+                    *p -= self.learning_rate * m_hat / (v_hat.sqrt() + 1e-8);
+                }
+            }
+        }
+    }
+}
+
+/// One choice made during self-play: the features of every legal play, which one was taken,
+/// and how much we want to imitate it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Decision {
+    pub candidates: Vec<Vec<f64>>,
+    pub chosen: usize,
+    pub weight: f64,
+}
+
+/// Plays one game dealt from `deck`, recording every decision along with the seat that made it
+pub fn play_and_record(
+    players: &[Box<dyn Player>],
+    deck: Vec<Card>,
+) -> (GameState, Vec<(usize, Decision)>) {
+    let mut decisions = Vec::new();
+    let rules = Rules {
+        n_players: players.len(),
+        ..Rules::default()
+    };
+
+    let game = play_game_on(players, rules, deck, |interface, play| {
+        let (plays, candidates): (Vec<Play>, Vec<Vec<f64>>) =
+            legal_play_features(interface).into_iter().unzip();
+
+        if let Some(chosen) = plays.iter().position(|p| p == play) {
+            let decision = Decision {
+                candidates,
+                chosen,
+                weight: 1.0,
+            };
+            decisions.push((interface.my_seat(), decision));
+        }
+    });
+
+    (game, decisions)
+}
+
+/// Plays `n_games` games, and keeps the decisions made by the winner of each one
+pub fn self_play_dataset(
+    n_games: usize,
+    make_players: impl Fn() -> Vec<Box<dyn Player>> + Sync,
+) -> Vec<Decision> {
+    (0..n_games)
+        .into_par_iter()
+        .flat_map(|_| {
+            let mut deck = entire_deck();
+            deck.shuffle(&mut thread_rng());
+            let (game, decisions) = play_and_record(&make_players(), deck);
+            let winner = game.winning_player().unwrap();
+
+            decisions
+                .into_iter()
+                .filter(|&(seat, _)| seat == winner)
+                .map(|(_, decision)| decision)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Fits the network to the dataset, returning the mean loss of each epoch
+pub fn train(
+    network: &mut Network,
+    dataset: &[Decision],
+    optimizer: &mut Optimizer,
+    epochs: usize,
+    batch_size: usize,
+    seed: u64,
+) -> Vec<f64> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut order: Vec<usize> = (0..dataset.len()).collect();
+    let mut losses = Vec::with_capacity(epochs);
+
+    for _ in 0..epochs {
+        order.shuffle(&mut rng);
+        let mut total_loss = 0.0;
+
+        for batch in order.chunks(batch_size.max(1)) {
+            let batch: Vec<&Decision> = batch.iter().map(|&i| &dataset[i]).collect();
+            total_loss += gradient_step(network, &batch, optimizer);
+        }
+
+        losses.push(total_loss / dataset.len().max(1) as f64);
+    }
+
+    losses
+}
+
+pub fn gradient_step(network: &mut Network, batch: &[&Decision], optimizer: &mut Optimizer) -> f64 {
+    // returns the total loss over the batch
+    let mut gradient = network.zeros_like();
+    let mut loss = 0.0;
+
+    for decision in batch {
+        loss += network.accumulate_gradient(decision, &mut gradient);
+    }
+
+    let scale = 1.0 / batch.len().max(1) as f64;
+    for g in gradient.parameters_mut() {
+        *g *= scale;
+    }
+
+    optimizer.apply(network, &gradient);
+
+    loss
+}
+
+pub struct NeuralPlayer {
+    network: Network,
+}
+
+impl NeuralPlayer {
+    pub fn new(network: Network) -> NeuralPlayer {
+        NeuralPlayer { network }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<NeuralPlayer> {
+        Ok(NeuralPlayer::new(Network::load(path)?))
+    }
+}
+
+impl Player for NeuralPlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        legal_play_features(game)
+            .into_iter()
+            .max_by_key(|(_play, features)| OrderedFloat(self.network.score(features)))
+            .expect("there is always a legal play")
+            .0
+    }
+}
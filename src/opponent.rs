@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use crate::events::{GameEvent, Observer};
+use crate::game::{GameError, GameRecord, SafeGameInterface};
+use crate::play::{Play, PlayKind};
+
+// what we have learned about how each of the people we play against tends to play, kept by name
+// so that it carries over from one game to the next. it is updated from what every player could
+// see when they decided (`Observer::on_decision`), or from saved games, and it is only ever counts,
+// so that a few games don't say much and a lot of games say a lot.
+// who is sitting where belongs to the game being played, so it is given to whoever needs it
+// rather than saved with the models. saved games don't have names, so their seats are "seat N"
+
+// below this many chances to do something, a player is taken to be more like everyone else
+const PRIOR_WEIGHT: f64 = 20.0;
+
+// how often the players in the games the control table was measured from passed when they could play,
+// which is what everyone is like until we know better, since the table already assumes it
+const PRIOR_PASS_RATE: f64 = 0.13;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OpponentModel {
+    pub games: u32,
+    pub could_beat: u32, // turns following someone else's play where they had something that beat it
+    pub passed_when_able: u32, // of those, how many they passed on
+    pub leads: u32,      // turns with control, not counting the first of the game
+    pub low_single_leads: u32, // of those, how many times they led the lowest card in their hand on its own
+    pub bomb_chances: u32, // turns where they could have played a four of a kind or strait flush
+    pub bombs_played: u32,
+}
+
+impl OpponentModel {
+    /// How often they pass when they could have played
+    pub fn pass_rate(&self) -> f64 {
+        rate(self.passed_when_able, self.could_beat, PRIOR_PASS_RATE)
+    }
+
+    fn observe(&mut self, game: &SafeGameInterface, play: &Play) {
+        let legal = game.legal_plays();
+
+        if game.have_control() {
+            if !game.is_first_turn() {
+                self.leads += 1;
+                let lowest = game.my_hand().iter().min();
+                if play.kind() == PlayKind::Single && play.ranking_card().as_ref() == lowest {
+                    self.low_single_leads += 1;
+                }
+            }
+        } else if legal.iter().any(|p| !p.is_pass()) {
+            self.could_beat += 1;
+            if play.is_pass() {
+                self.passed_when_able += 1;
+            }
+        }
+
+        if legal.iter().any(is_bomb) {
+            self.bomb_chances += 1;
+            if is_bomb(play) {
+                self.bombs_played += 1;
+            }
+        }
+    }
+}
+
+fn is_bomb(play: &Play) -> bool {
+    play.kind() == PlayKind::FourOfAKind || play.kind() == PlayKind::StraitFlush
+}
+
+fn rate(count: u32, out_of: u32, prior: f64) -> f64 {
+    (count as f64 + PRIOR_WEIGHT * prior) / (out_of as f64 + PRIOR_WEIGHT)
+}
+
+// the name a seat goes by when nobody has said who is sitting there
+fn seat_name(seat: usize) -> String {
+    format!("seat {}", seat)
+}
+
+/// A model for everyone we have played against
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Opponents {
+    pub models: BTreeMap<String, OpponentModel>,
+
+    #[serde(skip)]
+    seats: Vec<String>, // the names at the table in the games being observed
+    #[serde(skip)]
+    forfeited: Vec<bool>, // a bot plays for them, so what they do now isn't them
+}
+
+impl Opponents {
+    pub fn new() -> Opponents {
+        Opponents::default()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Opponents> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+
+    /// Says who is in each seat, for the games that are observed from now on
+    pub fn sit(&mut self, names: Vec<String>) {
+        self.seats = names;
+    }
+
+    /// The model of whoever `seats` says is in `seat`, if we know anything about them
+    pub fn at(&self, seats: &[String], seat: usize) -> Option<&OpponentModel> {
+        match seats.get(seat) {
+            Some(name) => self.models.get(name),
+            None => self.models.get(&seat_name(seat)),
+        }
+    }
+
+    pub fn add_record(&mut self, record: &GameRecord) -> Result<(), GameError> {
+        self.start_game(record.rules.n_players);
+        record.replay(|game, play| self.on_decision(&SafeGameInterface::from_game(game), play))?;
+        Ok(())
+    }
+
+    fn start_game(&mut self, n_players: usize) {
+        for seat in self.seats.len()..n_players {
+            self.seats.push(seat_name(seat));
+        }
+        let mut names: Vec<&String> = self.seats[..n_players].iter().collect();
+        names.sort();
+        names.dedup();
+        for name in names {
+            self.models.entry(name.clone()).or_default().games += 1;
+        }
+        self.forfeited = vec![false; n_players];
+    }
+
+    fn model_mut(&mut self, seat: usize) -> Option<&mut OpponentModel> {
+        if self.forfeited.get(seat) == Some(&true) {
+            return None;
+        }
+        self.models.get_mut(self.seats.get(seat)?)
+    }
+
+    /// How much more (above 1) or less likely than usual a play of ours is to be beaten,
+    /// given how the others still in the game, sitting as `seats` says, tend to pass when they could play
+    pub fn response_factor(&self, game: &SafeGameInterface, seats: &[String]) -> f64 {
+        let me = game.my_seat();
+        let willing: Vec<f64> = game
+            .hand_sizes()
+            .into_iter()
+            .enumerate()
+            .filter(|&(seat, size)| seat != me && size > 0)
+            .map(|(seat, _)| {
+                let pass_rate = self
+                    .at(seats, seat)
+                    .map_or(PRIOR_PASS_RATE, |m| m.pass_rate());
+                (1.0 - pass_rate) / (1.0 - PRIOR_PASS_RATE)
+            })
+            .collect();
+
+        if willing.is_empty() {
+            1.0
+        } else {
+            willing.iter().sum::<f64>() / willing.len() as f64
+        }
+    }
+}
+
+impl Observer for Opponents {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Dealt { hands, .. } => self.start_game(hands.len()),
+            GameEvent::Forfeited { seat, .. } => {
+                if let Some(forfeited) = self.forfeited.get_mut(*seat) {
+                    *forfeited = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_decision(&mut self, game: &SafeGameInterface, play: &Play) {
+        if let Some(model) = self.model_mut(game.my_seat()) {
+            model.observe(game, play);
+        }
+    }
+}
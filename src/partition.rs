@@ -0,0 +1,288 @@
+use serde::Serialize;
+
+use std::fmt;
+
+use crate::card::{Card, Rank};
+use crate::control::{ControlTable, Situation};
+use crate::play::finder::Finder;
+use crate::play::{Play, PlayKind};
+
+// splits a hand into plays that use every card exactly once, which is how you plan out a hand.
+// a partition costs the sum of what its plays cost, so the best ones can be built up from the best ways
+// of playing out every subset of the hand (a bitmask over its cards), starting from the empty one.
+// every partition is only counted once, by always taking out the play with the lowest card first
+
+// cards below this rank are low
+const LOW: Rank = Rank::Nine;
+
+/// The most cards that can be split up, since every subset of them gets looked at
+pub const MAX_CARDS: usize = 20;
+
+/// What makes a partition good. The cheapest partitions are the best
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Objective {
+    /// As few plays as possible, and of those, the plays most likely to win their tricks
+    FewestPlays,
+    /// As few low cards played on their own as possible, and then as few plays as possible
+    FewestLowSingles,
+}
+
+impl Objective {
+    pub fn name(self) -> &'static str {
+        match self {
+            Objective::FewestPlays => "fewest-plays",
+            Objective::FewestLowSingles => "fewest-low-singles",
+        }
+    }
+
+    pub fn cost(self, play: &Play) -> f64 {
+        match self {
+            Objective::FewestPlays => {
+                // small enough that thirteen of them don't add up to another play
+                let control = ControlTable::built_in().probability(play, Situation::default());
+                1.0 + 0.001 * (1.0 - control)
+            }
+            Objective::FewestLowSingles => {
+                let low_single =
+                    play.kind() == PlayKind::Single && play.ranking_card().unwrap().rank < LOW;
+                if low_single {
+                    1.0
+                } else {
+                    0.01
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Partition {
+    pub plays: Vec<Play>, // the play with the lowest card first
+    pub cost: f64,
+}
+
+/// The `k` best partitions of `hand` under `objective`, best first
+pub fn best_partitions(
+    hand: &[Card],
+    k: usize,
+    objective: Objective,
+) -> Result<Vec<Partition>, String> {
+    best_partitions_by(hand, k, |play| objective.cost(play))
+}
+
+/// The `k` partitions of `hand` where the plays cost the least in total, cheapest first.
+/// Hands of more than `MAX_CARDS` cards are too big
+pub fn best_partitions_by(
+    hand: &[Card],
+    k: usize,
+    cost: impl Fn(&Play) -> f64,
+) -> Result<Vec<Partition>, String> {
+    let mut cards = hand.to_vec();
+    cards.sort();
+    cards.dedup();
+    if cards.len() > MAX_CARDS {
+        return Err(format!(
+            "can't split up more than {} cards, and there are {}",
+            MAX_CARDS,
+            cards.len()
+        ));
+    }
+
+    if k == 0 {
+        return Ok(Vec::new());
+    }
+    if cards.is_empty() {
+        return Ok(vec![Partition {
+            plays: Vec::new(),
+            cost: 0.0,
+        }]);
+    }
+
+    let mask_of = |play: &Play| -> usize {
+        play.cards()
+            .iter()
+            .map(|card| 1 << cards.iter().position(|c| c == card).unwrap())
+            .fold(0, |mask, bit| mask | bit)
+    };
+
+    // the same five cards can be more than one kind of play (a strait flush is a strait and a flush too),
+    // but there's no reason to split them up as anything but the best of them
+    let mut plays: Vec<(usize, Play)> = Vec::new();
+    for play in Finder::new(cards.clone()).all_plays() {
+        let mask = mask_of(&play);
+        match plays.iter_mut().find(|(m, _)| *m == mask) {
+            Some((_, existing)) if *existing < play => *existing = play,
+            Some(_) => {}
+            None => plays.push((mask, play)),
+        }
+    }
+
+    // the plays that could be taken out of a subset, by the lowest card in them
+    let mut by_lowest: Vec<Vec<(usize, f64, usize)>> = vec![Vec::new(); cards.len()];
+    for (i, (mask, play)) in plays.iter().enumerate() {
+        by_lowest[mask.trailing_zeros() as usize].push((*mask, cost(play), i));
+    }
+
+    // best[mask] is the cheapest ways of playing out `mask`, each as
+    // (total cost, the play taken out first, which of the ways of playing out the rest)
+    let full = (1 << cards.len()) - 1;
+    let mut best: Vec<Vec<(f64, usize, usize)>> = vec![Vec::new(); full + 1];
+    best[0].push((0.0, usize::MAX, 0));
+
+    for mask in 1..=full {
+        let lowest = mask.trailing_zeros() as usize;
+
+        let mut ways = Vec::new();
+        for &(play_mask, play_cost, i) in by_lowest[lowest].iter() {
+            if play_mask & !mask != 0 {
+                continue;
+            }
+            for (rank, &(rest_cost, _, _)) in best[mask ^ play_mask].iter().enumerate() {
+                ways.push((play_cost + rest_cost, i, rank));
+            }
+        }
+
+        ways.sort_by(|a, b| a.0.total_cmp(&b.0));
+        ways.truncate(k);
+        best[mask] = ways;
+    }
+
+    let partitions = best[full]
+        .iter()
+        .map(|&(cost, first, rest)| {
+            let mut partition = Vec::new();
+            let (mut i, mut mask, mut rank) = (first, full, rest);
+            loop {
+                let (play_mask, play) = &plays[i];
+                partition.push(play.clone());
+                mask ^= play_mask;
+                if mask == 0 {
+                    break;
+                }
+                let (_, next, next_rank) = best[mask][rank];
+                i = next;
+                rank = next_rank;
+            }
+
+            Partition {
+                plays: partition,
+                cost,
+            }
+        })
+        .collect();
+
+    Ok(partitions)
+}
+
+impl fmt::Display for Partition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plays: Vec<String> = self.plays.iter().map(|p| format!("[{}]", p)).collect();
+        write!(
+            f,
+            "{} ({} plays, cost {:.3})",
+            plays.join(" "),
+            self.plays.len(),
+            self.cost
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{entire_deck, parse_cards};
+
+    // hands from real games, small enough to split up every way by hand
+    const HANDS: [&str; 4] = [
+        "3C 3S 4D 5H 6C 7S",
+        "8C 8S 8H KD KS 2C",
+        "3H 5H 7H 9H JH JS 4C",
+        "4S 5S 6S 7S 8S 8D 9C TC TD",
+    ];
+
+    // dearer for the higher plays, so that the costs of different partitions rarely tie
+    fn cost(play: &Play) -> f64 {
+        1.0 + play.ranking_card().unwrap().rank as usize as f64 / 100.0
+    }
+
+    // the cost of every partition of `cards`, taking out the play with the lowest card first
+    fn every_partition(cards: &[Card], plays: &[Vec<Card>]) -> Vec<f64> {
+        let lowest = match cards.iter().min() {
+            Some(&lowest) => lowest,
+            None => return vec![0.0],
+        };
+
+        let mut costs = Vec::new();
+        for play in plays.iter().filter(|p| p.contains(&lowest)) {
+            if play.iter().all(|c| cards.contains(c)) {
+                let rest: Vec<Card> = cards
+                    .iter()
+                    .filter(|c| !play.contains(c))
+                    .cloned()
+                    .collect();
+                let play_cost = cost(&Play::infer_from_cards(play.clone()).unwrap());
+                for rest_cost in every_partition(&rest, plays) {
+                    costs.push(play_cost + rest_cost);
+                }
+            }
+        }
+        costs
+    }
+
+    #[test]
+    fn finds_the_cheapest_partitions_in_order() {
+        for hand in HANDS.iter() {
+            let cards = parse_cards(hand).unwrap();
+
+            let mut plays: Vec<Vec<Card>> = Finder::new(cards.clone())
+                .all_plays()
+                .into_iter()
+                .map(|p| {
+                    let mut cards = p.into_cards();
+                    cards.sort();
+                    cards
+                })
+                .collect();
+            plays.sort();
+            plays.dedup();
+            let mut expected = every_partition(&cards, &plays);
+            expected.sort_by(|a, b| a.total_cmp(b));
+
+            for &k in [1, 3, 1000].iter() {
+                let partitions = best_partitions_by(&cards, k, cost).unwrap();
+                assert_eq!(partitions.len(), k.min(expected.len()), "{}", hand);
+
+                for (partition, expected) in partitions.iter().zip(expected.iter()) {
+                    assert!((partition.cost - expected).abs() < 1e-9, "{}", hand);
+
+                    let mut used: Vec<Card> = partition
+                        .plays
+                        .iter()
+                        .flat_map(|p| p.cards().to_vec())
+                        .collect();
+                    used.sort();
+                    let mut all = cards.clone();
+                    all.sort();
+                    assert_eq!(used, all, "{}", hand);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn too_many_cards() {
+        let deck = entire_deck();
+        assert!(best_partitions_by(&deck[..MAX_CARDS + 1], 1, cost).is_err());
+        assert!(best_partitions_by(&deck[..MAX_CARDS], 0, cost)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn empty_hand() {
+        let partitions = best_partitions_by(&[], 3, cost).unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert!(partitions[0].plays.is_empty());
+        assert_eq!(partitions[0].cost, 0.0);
+    }
+}
@@ -0,0 +1,148 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use std::cell::RefCell;
+use std::cmp::Reverse;
+
+use crate::bot::Player;
+use crate::card::Rank;
+use crate::game::SafeGameInterface;
+use crate::play::{Play, PlayKind};
+
+// bots with simple, recognizable ways of playing, for casual games and as easy opponents.
+// none of them look ahead, they just pick from the legal plays by a rule of thumb
+
+// the conservative bot won't follow with a card this high unless it has to
+const HOLD: Rank = Rank::Ace;
+
+// and it has to once someone has this few cards left
+const DANGER: usize = 3;
+
+/// Makes any legal play, every one as likely as every other
+pub struct RandomPlayer {
+    rng: RefCell<StdRng>,
+}
+
+impl RandomPlayer {
+    pub fn new(seed: u64) -> RandomPlayer {
+        RandomPlayer {
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        game.legal_plays()
+            .choose(&mut *self.rng.borrow_mut())
+            .cloned()
+            .expect("there is always a legal play")
+    }
+}
+
+/// Never passes when it can play, and gets rid of as many cards as it can with the lowest play that does
+pub struct GreedyLowPlayer;
+
+impl Player for GreedyLowPlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        plays(game)
+            .into_iter()
+            .max_by_key(|p| (p.cards().len(), Reverse(p.clone())))
+            .unwrap_or_else(Play::pass)
+    }
+}
+
+/// Never passes when it can play, and otherwise makes the lowest play it has
+pub struct PassAvoidingPlayer;
+
+impl Player for PassAvoidingPlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        plays(game).into_iter().min().unwrap_or_else(Play::pass)
+    }
+}
+
+/// Never passes when it can play, and plays as many cards as it can, as high as they go,
+/// to take control and keep it
+pub struct AggressivePlayer;
+
+impl Player for AggressivePlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        plays(game)
+            .into_iter()
+            .max_by_key(|p| (p.cards().len(), p.clone()))
+            .unwrap_or_else(Play::pass)
+    }
+}
+
+/// Leads with its lowest single, follows with the lowest play that beats the table,
+/// and passes rather than give up a high card unless someone is about to go out
+pub struct ConservativePlayer;
+
+impl Player for ConservativePlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        let plays = plays(game);
+
+        if game.have_control() {
+            let lowest_single = plays.iter().filter(|p| p.kind() == PlayKind::Single).min();
+            return lowest_single
+                .or_else(|| plays.iter().min())
+                .cloned()
+                .expect("there is always a play with control");
+        }
+
+        let danger = game
+            .hand_sizes()
+            .into_iter()
+            .enumerate()
+            .any(|(seat, size)| seat != game.my_seat() && size > 0 && size <= DANGER);
+
+        match plays.into_iter().min() {
+            Some(play) if danger || play.cards().iter().all(|c| c.rank < HOLD) => play,
+            _ => Play::pass(),
+        }
+    }
+}
+
+/// Plays like `player`, except that `rate` of the time it makes some other legal play instead
+pub struct CarelessPlayer {
+    player: Box<dyn Player>,
+    rate: f64,
+    rng: RefCell<StdRng>,
+}
+
+impl CarelessPlayer {
+    pub fn new(player: Box<dyn Player>, rate: f64, seed: u64) -> CarelessPlayer {
+        CarelessPlayer {
+            player,
+            rate,
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl Player for CarelessPlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        let chosen = self.player.choose_play(game);
+
+        let rng = &mut *self.rng.borrow_mut();
+        if !rng.gen_bool(self.rate) {
+            return chosen;
+        }
+
+        let others: Vec<Play> = game
+            .legal_plays()
+            .into_iter()
+            .filter(|p| *p != chosen)
+            .collect();
+        others.choose(rng).cloned().unwrap_or(chosen)
+    }
+}
+
+// the legal plays that aren't passes
+fn plays(game: &SafeGameInterface) -> Vec<Play> {
+    game.legal_plays()
+        .into_iter()
+        .filter(|p| !p.is_pass())
+        .collect()
+}
@@ -5,6 +5,7 @@ use finder::Finder;
 
 use crate::card::Card;
 use std::cmp::Ordering;
+use std::fmt;
 
 
 
@@ -58,6 +59,22 @@ impl PartialOrd for Play {
     }
 }
 
+impl fmt::Display for Play {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_pass() {
+            return f.write_str("pass");
+        }
+
+        for (i, card) in self.cards.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", card)?;
+        }
+
+        Ok(())
+    }
+}
 
 impl Play {
     pub fn new(kind: PlayKind, ranking_card: Card, cards: Vec<Card>) -> Play {
@@ -68,6 +85,14 @@ impl Play {
         }
     }
 
+    pub fn pass() -> Play {
+        Play {
+            cards: vec![],
+            kind: PlayKind::Pass,
+            ranking_card: None,
+        }
+    }
+
     pub fn is_pass(&self) -> bool {
         self.kind == PlayKind::Pass
     }
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+use std::io::{self, BufRead, Write};
+
+use crate::card::Card;
+use crate::game::Observation;
+use crate::hint::Hint;
+use crate::play::Play;
+
+// the messages that the multiplayer server and its clients send each other.
+// every message is a single line of json, with a "type" field saying which message it is
+// (over a websocket, every text message is one of these instead).
+// cards look like {"rank":"Three","suit":"Clubs"}, and plays look like
+// {"kind":"Pair","cards":[...],"ranking_card":{...}}, the same as in saved games.
+//
+// client -> server
+//     {"type":"join","table":"table0","seat":null}
+//         sit down at a table. seat can be null to take any free seat.
+//         answered with welcome, or error if the table or seat doesn't exist or is taken
+//     {"type":"rejoin","table":"table0","token":"..."}
+//         take back a seat after being disconnected, using the token from welcome.
+//         answered with welcome, then state (and your_turn if we were being waited on)
+//     {"type":"play","cards":[...]}
+//         play some cards. only allowed after your_turn, answered with rejected if it's illegal
+//     {"type":"pass"}
+//         the same as a play with no cards
+//     {"type":"assist","on":true}
+//         turn hints and warnings on or off for your seat, see `hint`. only allowed after welcome
+//
+// server -> client
+//     {"type":"welcome","table":"table0","seat":1,"token":"..."}
+//     {"type":"waiting","missing":2}                 the game starts once every seat is taken
+//     {"type":"game_started","game":0,"observation":{...}}
+//     {"type":"state","observation":{...}}           sent to every seat after anything happens
+//     {"type":"your_turn","observation":{...}}       we are waiting on a play or pass from you
+//     {"type":"hint","hint":{...}}                   with assist on, sent just before your_turn
+//     {"type":"warning","warnings":["..."]}          with assist on, your play might be a mistake.
+//                                                    send the same play again to make it anyway
+//     {"type":"played","seat":2,"play":{...}}        public, sent to every seat (passes included)
+//     {"type":"rejected","reason":"TooLow"}          your play was illegal, try again
+//     {"type":"game_over","placements":[1,0,3,2]}    the place of every seat, 0 is first
+//     {"type":"error","message":"..."}
+//
+// an observation only contains what that seat is allowed to know, see `game::Observation`
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Join { table: String, seat: Option<usize> },
+    Rejoin { table: String, token: String },
+    Play { cards: Vec<Card> },
+    Pass,
+    Assist { on: bool },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Welcome {
+        table: String,
+        seat: usize,
+        token: String,
+    },
+    Waiting {
+        missing: usize,
+    },
+    GameStarted {
+        game: usize,
+        observation: Observation,
+    },
+    State {
+        observation: Observation,
+    },
+    YourTurn {
+        observation: Observation,
+    },
+    Hint {
+        hint: Hint,
+    },
+    Warning {
+        warnings: Vec<String>,
+    },
+    Played {
+        seat: usize,
+        play: Play,
+    },
+    Rejected {
+        reason: String,
+    },
+    GameOver {
+        placements: Vec<usize>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Writes `message` as a single line
+pub fn send<T: Serialize>(out: &mut impl Write, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    out.write_all(line.as_bytes())?;
+    out.flush()
+}
+
+/// Reads the next message, or `None` once the other side has hung up
+pub fn receive<T>(input: &mut impl BufRead) -> io::Result<Option<T>>
+where
+    for<'de> T: Deserialize<'de>,
+{
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if !line.trim().is_empty() {
+            break;
+        }
+    }
+
+    serde_json::from_str(&line)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
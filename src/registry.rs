@@ -0,0 +1,256 @@
+use rand::{thread_rng, Rng};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::bot::{HumanPlayer, MachinePlayer, Player, SearchBudget, N_PARAMETERS};
+use crate::control::ControlTable;
+use crate::engine::{self, SubprocessPlayer};
+use crate::mcts::{self, MctsPlayer};
+use crate::neural::{Network, NeuralPlayer};
+use crate::opponent::Opponents;
+use crate::personality::{
+    AggressivePlayer, CarelessPlayer, ConservativePlayer, GreedyLowPlayer, PassAvoidingPlayer,
+    RandomPlayer,
+};
+use crate::reinforce::PolicyPlayer;
+use crate::train::load_parameters;
+
+// every kind of bot that can be asked for by name, with its settings after a colon,
+// like `machine:depth=4,time_ms=50` or `mcts:iters=2000`. the difficulty levels are just
+// names for other specs, and any setting given with them overrides theirs.
+// every bot can also be told to make mistakes on purpose with `mistakes=<fraction of turns>`
+
+/// The bots, and what their settings are
+pub const BOTS: [(&str, &str); 10] = [
+    (
+        "machine",
+        "the search bot (depth, time_ms, nodes, params=FILE, control=FILE, opponents=FILE)",
+    ),
+    ("mcts", "monte carlo search (iters, time_ms, seed)"),
+    (
+        "neural",
+        "a network trained with --mode neural (weights=FILE)",
+    ),
+    (
+        "policy",
+        "a network trained with --mode reinforce (weights=FILE, temperature, seed)",
+    ),
+    (
+        "engine",
+        "an outside program speaking the engine protocol (path, timeout_ms)",
+    ),
+    ("random-legal", "any legal play at random (seed)"),
+    ("greedy-lowest", "as many cards as it can, as low as it can"),
+    ("pass-avoiding", "the lowest play it can, never passing"),
+    ("aggressive", "as many cards as it can, as high as it can"),
+    (
+        "conservative",
+        "the lowest play it can, holding on to its aces and twos",
+    ),
+];
+
+/// The simple bots that anything smarter should beat, for `tournament --baselines`
+pub const BASELINES: [&str; 3] = ["random-legal", "greedy-lowest", "pass-avoiding"];
+
+/// What the difficulty levels stand for. Against each other and greedy-lowest over 80 games,
+/// they won about 6%, 31% and 41% of the time (and greedy-lowest 21%)
+pub const LEVELS: [(&str, &str); 3] = [
+    ("beginner", "greedy-lowest:mistakes=0.3"),
+    ("intermediate", "mcts:iters=200"),
+    ("expert", "mcts:iters=2000"),
+];
+
+/// Builds a player from a spec like `machine:depth=4,time_ms=50`.
+/// `parameters` are for machine bots that don't name their own
+pub fn make_player(spec: &str, parameters: [f64; N_PARAMETERS]) -> Result<Box<dyn Player>, String> {
+    make_seated_player(spec, parameters, &[])
+}
+
+/// Builds a player the same way as `make_player`, for a table where `seats` are the names of
+/// whoever is sitting in each seat, so that `opponents=FILE` knows who is who
+pub fn make_seated_player(
+    spec: &str,
+    parameters: [f64; N_PARAMETERS],
+    seats: &[String],
+) -> Result<Box<dyn Player>, String> {
+    let (mut name, mut settings) = parse_spec(spec)?;
+
+    if let Some(&(_, level)) = LEVELS.iter().find(|&&(level, _)| level == name) {
+        let (level_name, level_settings) = parse_spec(level)?;
+        name = level_name;
+        settings = level_settings.into_iter().chain(settings).collect();
+    }
+
+    let values: HashMap<&str, &str> = settings
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+
+    let get = |key: &str| -> Result<Option<u64>, String> {
+        values
+            .get(key)
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| format!("bad value for {} in {}", key, spec))
+            })
+            .transpose()
+    };
+    let get_f64 = |key: &str| -> Result<Option<f64>, String> {
+        values
+            .get(key)
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| format!("bad value for {} in {}", key, spec))
+            })
+            .transpose()
+    };
+    let known = settings_for(&name).ok_or_else(|| format!("unknown bot: {}", name))?;
+    if let Some((key, _)) = settings
+        .iter()
+        .find(|(key, _)| !known.contains(&key.as_str()) && key != "seed" && key != "mistakes")
+    {
+        return Err(format!("unknown setting {} for {} in {}", key, name, spec));
+    }
+
+    let seed = get("seed")?.unwrap_or_else(|| thread_rng().gen());
+
+    let player: Box<dyn Player> = match name.as_str() {
+        "human" => Box::new(HumanPlayer),
+        "machine" => {
+            let parameters = match values.get("params") {
+                Some(path) => read_parameters(path)?,
+                None => parameters,
+            };
+
+            let default = SearchBudget::default();
+            let budget = SearchBudget {
+                max_depth: get("depth")?.map_or(default.max_depth, |d| d as usize),
+                time_limit: get("time_ms")?.map(Duration::from_millis),
+                node_limit: get("nodes")?.map(|n| n as usize),
+            };
+
+            let mut player = MachinePlayer::with_budget(parameters, budget);
+            if let Some(path) = values.get("control") {
+                let control = ControlTable::load(path)
+                    .map_err(|e| format!("couldn't read control table from {}: {}", path, e))?;
+                player = player.with_control(Arc::new(control));
+            }
+            if let Some(path) = values.get("opponents") {
+                let opponents = Arc::new(read_opponents(path)?);
+                player = player.with_opponents(opponents, seats.to_vec());
+            }
+            Box::new(player)
+        }
+        "mcts" => {
+            let iterations = get("iters")?.map_or(mcts::DEFAULT_ITERATIONS, |i| i as usize);
+            let time_limit = get("time_ms")?.map(Duration::from_millis);
+            Box::new(MctsPlayer::new(iterations, time_limit, seed))
+        }
+        "neural" => {
+            let path = values
+                .get("weights")
+                .ok_or_else(|| format!("{} needs weights=<file>", spec))?;
+            Box::new(NeuralPlayer::new(read_network(path)?))
+        }
+        "policy" => {
+            let path = values
+                .get("weights")
+                .ok_or_else(|| format!("{} needs weights=<file>", spec))?;
+            let temperature = get_f64("temperature")?.unwrap_or(1.0);
+            Box::new(PolicyPlayer::new(read_network(path)?, temperature, seed))
+        }
+        "engine" => {
+            let path = values
+                .get("path")
+                .ok_or_else(|| format!("{} needs path=<program>", spec))?;
+            let timeout = get("timeout_ms")?.map_or(engine::DEFAULT_TIMEOUT, Duration::from_millis);
+            Box::new(SubprocessPlayer::new(path, &[], timeout))
+        }
+        "random-legal" => Box::new(RandomPlayer::new(seed)),
+        "greedy-lowest" => Box::new(GreedyLowPlayer),
+        "pass-avoiding" => Box::new(PassAvoidingPlayer),
+        "aggressive" => Box::new(AggressivePlayer),
+        "conservative" => Box::new(ConservativePlayer),
+        _ => return Err(format!("unknown bot: {}", name)),
+    };
+
+    match get_f64("mistakes")? {
+        Some(rate) if !(0.0..=1.0).contains(&rate) => {
+            Err(format!("mistakes in {} should be between 0 and 1", spec))
+        }
+        Some(rate) if rate > 0.0 => {
+            // not the same numbers as the bot's own randomness, which may use the same seed
+            Ok(Box::new(CarelessPlayer::new(player, rate, seed ^ 1)))
+        }
+        _ => Ok(player),
+    }
+}
+
+// the settings each bot understands, besides the `seed` and `mistakes` that all of them take
+fn settings_for(name: &str) -> Option<&'static [&'static str]> {
+    let settings: &[&str] = match name {
+        "machine" => &[
+            "depth",
+            "time_ms",
+            "nodes",
+            "params",
+            "control",
+            "opponents",
+        ],
+        "mcts" => &["iters", "time_ms"],
+        "neural" => &["weights"],
+        "policy" => &["weights", "temperature"],
+        "engine" => &["path", "timeout_ms"],
+        "human" | "random-legal" | "greedy-lowest" | "pass-avoiding" | "aggressive"
+        | "conservative" => &[],
+        _ => return None,
+    };
+    Some(settings)
+}
+
+/// `spec` with `seed=` added, unless it already has a seed of its own
+pub fn with_seed(spec: &str, seed: u64) -> String {
+    match parse_spec(spec) {
+        Ok((_, settings)) if settings.iter().any(|(key, _)| key == "seed") => spec.to_string(),
+        _ if spec.contains(':') => format!("{},seed={}", spec, seed),
+        _ => format!("{}:seed={}", spec, seed),
+    }
+}
+
+// the name of the bot, and its settings in the order they were given
+fn parse_spec(spec: &str) -> Result<(String, Vec<(String, String)>), String> {
+    let (name, settings) = match spec.find(':') {
+        Some(colon) => (&spec[..colon], &spec[colon + 1..]),
+        None => (spec, ""),
+    };
+
+    let settings = settings
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(|setting| {
+            let equals = setting
+                .find('=')
+                .ok_or_else(|| format!("expected key=value in {}", spec))?;
+            Ok((
+                setting[..equals].to_string(),
+                setting[equals + 1..].to_string(),
+            ))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok((name.to_string(), settings))
+}
+
+pub fn read_parameters(path: &str) -> Result<[f64; N_PARAMETERS], String> {
+    load_parameters(path).map_err(|e| format!("couldn't read parameters from {}: {}", path, e))
+}
+
+pub fn read_opponents(path: &str) -> Result<Opponents, String> {
+    Opponents::load(path).map_err(|e| format!("couldn't read opponent models from {}: {}", path, e))
+}
+
+pub fn read_network(path: &str) -> Result<Network, String> {
+    Network::load(path).map_err(|e| format!("couldn't read network from {}: {}", path, e))
+}
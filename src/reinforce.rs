@@ -0,0 +1,247 @@
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
+
+use std::cell::RefCell;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::bot::{MachinePlayer, Player};
+use crate::card::entire_deck;
+use crate::game::{Rules, SafeGameInterface};
+use crate::neural::{
+    gradient_step, legal_play_features, play_and_record, Decision, Network, NeuralPlayer, Optimizer,
+};
+use crate::play::Play;
+use crate::train::{play_game_on, DEFAULT_PARAMETERS};
+
+// self-play reinforcement learning (REINFORCE) for the network in `neural`.
+// every seat samples from the same stochastic policy, and once the game is over every decision
+// a seat made is pushed towards or away from, depending on how well that seat placed
+
+/// Samples plays from the softmax of the network's scores
+pub struct PolicyPlayer {
+    network: Network,
+    temperature: f64,
+    rng: RefCell<StdRng>,
+}
+
+impl PolicyPlayer {
+    pub fn new(network: Network, temperature: f64, seed: u64) -> PolicyPlayer {
+        PolicyPlayer {
+            network,
+            temperature,
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl Player for PolicyPlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        let (mut plays, candidates): (Vec<Play>, Vec<Vec<f64>>) =
+            legal_play_features(game).into_iter().unzip();
+
+        let probabilities = self
+            .network
+            .policy_with_temperature(&candidates, self.temperature);
+
+        let index = WeightedIndex::new(&probabilities)
+            .expect("there is always a legal play")
+            .sample(&mut *self.rng.borrow_mut());
+
+        plays.swap_remove(index)
+    }
+}
+
+pub struct ReinforceConfig {
+    pub iterations: usize,
+    pub games_per_iteration: usize,
+    pub learning_rate: f64,
+    pub temperature: f64, // only changes how we explore, the updates are always made at temperature 1
+    pub baseline_decay: f64, // how quickly the reward baseline forgets old games
+    pub eval_every: usize,
+    pub eval_games: usize,
+    pub checkpoint_every: usize,
+    pub checkpoint_dir: PathBuf,
+    pub log_path: PathBuf, // the learning curve, as csv
+    pub seed: u64,
+}
+
+impl Default for ReinforceConfig {
+    fn default() -> ReinforceConfig {
+        ReinforceConfig {
+            iterations: 1000,
+            games_per_iteration: 32,
+            learning_rate: 1e-3,
+            temperature: 1.0,
+            baseline_decay: 0.95,
+            eval_every: 25,
+            eval_games: 20,
+            checkpoint_every: 25,
+            checkpoint_dir: PathBuf::from("checkpoints"),
+            log_path: PathBuf::from("reinforce.csv"),
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Evaluation {
+    pub win_rate: f64,
+    pub mean_placement: f64,
+}
+
+/// Trains `network` in place with REINFORCE, logging and checkpointing as it goes
+pub fn reinforce(network: &mut Network, config: &ReinforceConfig) -> io::Result<()> {
+    fs::create_dir_all(&config.checkpoint_dir)?;
+
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&config.log_path)?;
+    if log.metadata()?.len() == 0 {
+        writeln!(log, "iteration,loss,entropy,eval_win_rate,eval_mean_placement")?;
+    }
+
+    let mut optimizer = Optimizer::adam(config.learning_rate);
+    let mut baseline = 0.0;
+
+    for iteration in 0..config.iterations {
+        let seed_base = config
+            .seed
+            .wrapping_add((iteration * config.games_per_iteration) as u64);
+
+        // every decision, along with the reward of the seat that made it
+        let rewarded: Vec<(f64, Decision)> = (0..config.games_per_iteration)
+            .into_par_iter()
+            .flat_map(|g| {
+                // one stream of numbers for each seat's sampling, and one more for the deal
+                let game_seed = seed_base.wrapping_add(g as u64).wrapping_mul(5);
+                let players: Vec<Box<dyn Player>> = (0..4)
+                    .map(|seat| {
+                        let seed = game_seed.wrapping_add(seat);
+                        Box::new(PolicyPlayer::new(network.clone(), config.temperature, seed))
+                            as Box<dyn Player>
+                    })
+                    .collect();
+
+                let mut deck = entire_deck();
+                deck.shuffle(&mut StdRng::seed_from_u64(game_seed.wrapping_add(4)));
+                let (game, decisions) = play_and_record(&players, deck);
+                let placements = game.placements();
+
+                decisions
+                    .into_iter()
+                    .map(|(seat, decision)| (placement_reward(placements[seat], 4), decision))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        // advantage = how much better this seat did than we usually do
+        let mut batch = Vec::new();
+        let mut total_reward = 0.0;
+        let mut n_rewards = 0.0;
+
+        for (reward, mut decision) in rewarded {
+            decision.weight = reward - baseline;
+
+            total_reward += reward;
+            n_rewards += 1.0;
+            batch.push(decision);
+        }
+
+        if n_rewards > 0.0 {
+            let mean_reward = total_reward / n_rewards;
+            baseline = config.baseline_decay * baseline + (1.0 - config.baseline_decay) * mean_reward;
+        }
+
+        let entropy = mean_entropy(network, &batch, config.temperature);
+        let references: Vec<&Decision> = batch.iter().collect();
+        let loss = gradient_step(network, &references, &mut optimizer) / batch.len().max(1) as f64;
+
+        let evaluation = if (iteration + 1) % config.eval_every.max(1) == 0 {
+            Some(evaluate(network, config.eval_games, config.seed))
+        } else {
+            None
+        };
+
+        match evaluation {
+            Some(e) => {
+                println!(
+                    "iteration {}: loss {:.4}, entropy {:.4}, win rate {:.3}, mean placement {:.3}",
+                    iteration, loss, entropy, e.win_rate, e.mean_placement
+                );
+                writeln!(log, "{},{},{},{},{}", iteration, loss, entropy, e.win_rate, e.mean_placement)?;
+            }
+            None => {
+                println!("iteration {}: loss {:.4}, entropy {:.4}", iteration, loss, entropy);
+                writeln!(log, "{},{},{},,", iteration, loss, entropy)?;
+            }
+        }
+
+        if (iteration + 1) % config.checkpoint_every.max(1) == 0 {
+            let path = config
+                .checkpoint_dir
+                .join(format!("checkpoint_{}.json", iteration + 1));
+            network.save(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn placement_reward(placement: usize, n_players: usize) -> f64 {
+    // first place gets 1, last place gets -1
+    1.0 - 2.0 * placement as f64 / (n_players - 1) as f64
+}
+
+fn mean_entropy(network: &Network, batch: &[Decision], temperature: f64) -> f64 {
+    let total: f64 = batch
+        .iter()
+        .map(|d| {
+            network
+                .policy_with_temperature(&d.candidates, temperature)
+                .iter()
+                .filter(|&&p| p > 0.0)
+                .map(|p| -p * p.ln())
+                .sum::<f64>()
+        })
+        .sum();
+
+    total / batch.len().max(1) as f64
+}
+
+/// Plays the greedy version of the network against three `MachinePlayer`s with `DEFAULT_PARAMETERS`,
+/// rotating which seat it sits in, on the same `n_games` deals every time for the same `seed`
+pub fn evaluate(network: &Network, n_games: usize, seed: u64) -> Evaluation {
+    let placements: Vec<usize> = (0..n_games)
+        .into_par_iter()
+        .map(|g| {
+            let seat = g % 4;
+
+            let players: Vec<Box<dyn Player>> = (0..4)
+                .map(|i| {
+                    if i == seat {
+                        Box::new(NeuralPlayer::new(network.clone())) as Box<dyn Player>
+                    } else {
+                        Box::new(MachinePlayer::new(DEFAULT_PARAMETERS))
+                    }
+                })
+                .collect();
+
+            let mut deck = entire_deck();
+            deck.shuffle(&mut StdRng::seed_from_u64(seed.wrapping_add(g as u64)));
+            play_game_on(&players, Rules::default(), deck, |_, _| {}).placements()[seat]
+        })
+        .collect();
+
+    let n = placements.len().max(1) as f64;
+
+    Evaluation {
+        win_rate: placements.iter().filter(|&&p| p == 0).count() as f64 / n,
+        mean_placement: placements.iter().sum::<usize>() as f64 / n,
+    }
+}
@@ -0,0 +1,82 @@
+use serde::Serialize;
+
+use std::fmt;
+
+use crate::bot::{CostCase, FirstStep};
+use crate::play::Play;
+
+// an explanation of why `MachinePlayer` made a decision, from `MachinePlayer::choose_play_with_report`
+
+#[derive(Debug, Serialize)]
+pub struct DecisionReport {
+    pub chosen: Play,
+    pub depth: usize, // in cards, of the deepest search that finished
+    pub candidates: Vec<CandidateReport>, // best first
+}
+
+/// Everything the search found out about the sequences that start with `play`
+#[derive(Debug, Serialize)]
+pub struct CandidateReport {
+    pub play: Play,
+    pub leaves: f64,
+    pub mean_cost: f64,
+    pub min_cost: f64,
+    pub best_continuation: Vec<Play>, // the rest of the cheapest sequence
+    pub first_step: FirstStep,
+    pub branches: Vec<BranchUse>, // how the rest of the sequences were priced
+}
+
+#[derive(Debug, Serialize)]
+pub struct BranchUse {
+    pub case: CostCase,
+    pub uses: f64,
+    pub total_cost: f64,
+}
+
+impl DecisionReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl fmt::Display for DecisionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "chose {} (searched {} cards deep)", self.chosen, self.depth)?;
+        writeln!(
+            f,
+            "{:<20} {:>10} {:>10} {:>10}  {:<50} best continuation",
+            "play", "leaves", "mean", "min", "first step"
+        )?;
+
+        for candidate in self.candidates.iter() {
+            let continuation: Vec<String> = candidate
+                .best_continuation
+                .iter()
+                .map(|p| p.to_string())
+                .collect();
+
+            writeln!(
+                f,
+                "{:<20} {:>10} {:>10.3} {:>10.3}  {:<50} {}",
+                candidate.play.to_string(),
+                candidate.leaves,
+                candidate.mean_cost,
+                candidate.min_cost,
+                candidate.first_step.to_string(),
+                continuation.join(" | "),
+            )?;
+
+            for branch in candidate.branches.iter() {
+                writeln!(
+                    f,
+                    "    {:<16} used {:>10}, costing {:.3}",
+                    branch.case.to_string(),
+                    branch.uses,
+                    branch.total_cost,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use std::fmt;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::card::Card;
+use crate::game::{GameRecord, GameState, Position, SafeGameInterface};
+use crate::mcts;
+use crate::play::Play;
+
+// goes back over a saved game and finds the decisions where one seat gave away the most.
+// every decision is searched with `mcts` from what that seat could see at the time, and it is a blunder
+// when the best play we found wins noticeably more often than the one that was made.
+// the best play is the one the search spent the most time on, like `MctsPlayer` chooses,
+// since a play that got lucky in its first few playouts can have any win rate at all
+
+pub struct ReviewConfig {
+    pub seat: usize,
+    pub threshold: f64, // how much win probability a play has to give away to count
+    pub iterations: usize, // playouts for every decision
+    pub time_limit: Option<Duration>, // for every decision
+    pub seed: u64,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> ReviewConfig {
+        ReviewConfig {
+            seat: 0,
+            threshold: 0.1,
+            iterations: mcts::DEFAULT_ITERATIONS,
+            time_limit: None,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Review {
+    pub seat: usize,
+    pub threshold: f64,
+    pub decisions: usize, // that had more than one legal play
+    pub blunders: Vec<Blunder>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Blunder {
+    pub turn: usize,     // counting every play and pass in the game from 0
+    pub hand: Vec<Card>, // sorted
+    pub table: Option<Play>,
+    pub played: Play,
+    pub played_win_rate: f64,
+    pub played_visits: usize, // playouts that started with it
+    pub best: Play,
+    pub best_win_rate: f64,
+    pub best_visits: usize,
+}
+
+impl Blunder {
+    pub fn loss(&self) -> f64 {
+        self.best_win_rate - self.played_win_rate
+    }
+}
+
+pub fn review(record: &GameRecord, config: &ReviewConfig) -> Result<Review, String> {
+    if config.seat >= record.rules.n_players {
+        return Err(format!("no seat {}", config.seat));
+    }
+
+    // everything the seat had to decide, as positions so that they can be searched in parallel
+    let mut decisions: Vec<(usize, Position, Play)> = Vec::new();
+    let mut turn = 0;
+    record
+        .replay(|game, play| {
+            if game.current_player == config.seat && game.legal_plays().len() > 1 {
+                decisions.push((turn, game.position(), play.clone()));
+            }
+            turn += 1;
+        })
+        .map_err(|e| format!("the record contains an illegal play: {:?}", e))?;
+
+    let n_decisions = decisions.len();
+
+    let mut blunders: Vec<Blunder> = decisions
+        .into_par_iter()
+        .filter_map(|(turn, position, played)| {
+            let game = GameState::from_position(&position).expect("it came from a real game");
+            let interface = SafeGameInterface::from_game(&game);
+
+            let mut rng = StdRng::seed_from_u64(config.seed.wrapping_add(turn as u64));
+            let stats = mcts::search(&interface, config.iterations, config.time_limit, &mut rng);
+
+            let played_stats = stats.iter().find(|s| s.play == played)?;
+            let best = stats.iter().max_by_key(|s| s.visits)?;
+
+            let mut hand = game.my_hand().to_vec();
+            hand.sort();
+
+            let blunder = Blunder {
+                turn,
+                hand,
+                table: if game.have_control() {
+                    None
+                } else {
+                    game.get_play_on_table().cloned()
+                },
+                played,
+                played_win_rate: played_stats.win_rate(),
+                played_visits: played_stats.visits,
+                best: best.play.clone(),
+                best_win_rate: best.win_rate(),
+                best_visits: best.visits,
+            };
+
+            if blunder.loss() > config.threshold {
+                Some(blunder)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    blunders.sort_by_key(|b| b.turn);
+
+    Ok(Review {
+        seat: config.seat,
+        threshold: config.threshold,
+        decisions: n_decisions,
+        blunders,
+    })
+}
+
+impl Review {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+
+        html.push_str(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>pusoy review</title>\n\
+             <style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             table { border-collapse: collapse; }\n\
+             th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }\n\
+             .loss { color: #b00; font-weight: bold; }\n\
+             </style>\n</head>\n<body>\n",
+        );
+
+        let _ = writeln!(
+            html,
+            "<h1>Review of seat {}</h1>\n<p>{} of {} decisions gave away more than {:.0}% win probability.</p>",
+            self.seat,
+            self.blunders.len(),
+            self.decisions,
+            self.threshold * 100.0
+        );
+
+        if !self.blunders.is_empty() {
+            html.push_str(
+                "<table>\n<tr><th>turn</th><th>hand</th><th>table</th><th>played</th>\
+                 <th>better</th><th>given away</th></tr>\n",
+            );
+
+            for blunder in self.blunders.iter() {
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{} ({:.0}%, {} playouts)</td>\
+                     <td>{} ({:.0}%, {} playouts)</td><td class=\"loss\">{:.0}%</td></tr>",
+                    blunder.turn,
+                    escape(&cards_to_string(&blunder.hand)),
+                    escape(&table_to_string(&blunder.table)),
+                    escape(&blunder.played.to_string()),
+                    blunder.played_win_rate * 100.0,
+                    blunder.played_visits,
+                    escape(&blunder.best.to_string()),
+                    blunder.best_win_rate * 100.0,
+                    blunder.best_visits,
+                    blunder.loss() * 100.0
+                );
+            }
+
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+impl fmt::Display for Review {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "seat {}: {} of {} decisions gave away more than {:.0}% win probability",
+            self.seat,
+            self.blunders.len(),
+            self.decisions,
+            self.threshold * 100.0
+        )?;
+
+        for blunder in self.blunders.iter() {
+            writeln!(f)?;
+            writeln!(f, "turn {}", blunder.turn)?;
+            writeln!(f, "    hand:   {}", cards_to_string(&blunder.hand))?;
+            writeln!(f, "    table:  {}", table_to_string(&blunder.table))?;
+            writeln!(
+                f,
+                "    played: {} ({:.0}% to win, {} playouts)",
+                blunder.played,
+                blunder.played_win_rate * 100.0,
+                blunder.played_visits
+            )?;
+            writeln!(
+                f,
+                "    better: {} ({:.0}% to win, {} playouts)",
+                blunder.best,
+                blunder.best_win_rate * 100.0,
+                blunder.best_visits
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn cards_to_string(cards: &[Card]) -> String {
+    let cards: Vec<String> = cards.iter().map(|c| c.to_string()).collect();
+    cards.join(" ")
+}
+
+fn table_to_string(table: &Option<Play>) -> String {
+    match table {
+        Some(play) => play.to_string(),
+        None => "nothing".to_string(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,272 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, SeedableRng};
+
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+use crate::bot::Player;
+use crate::card::{entire_deck, Card};
+use crate::events::{deal_events, play_events, GameEvent, Observer};
+use crate::game::{GameRecord, GameState, Rules, SafeGameInterface};
+use crate::play::Play;
+
+// the loop that asks players for plays until the game is over, telling every observer what happens.
+// everything that drives players goes through here.
+// a player that panics or makes an illegal play is forfeited: from then on it passes whenever it can,
+// makes the smallest play it can otherwise, and places behind everyone who wasn't forfeited
+
+/// Plays `game` out to the end between `players`, one for each seat
+pub fn run_game(
+    players: &[Box<dyn Player>],
+    game: GameState,
+    observers: &mut [&mut dyn Observer],
+) -> GameState {
+    drive(players, game, observers).game
+}
+
+/// One game between some players, set up with `new` and the `with_` methods, then played with `run`
+pub struct Match<'a> {
+    players: Vec<Box<dyn Player>>,
+    rules: Rules,
+    deck: Option<Vec<Card>>, // shuffled at random when this isn't given
+    observers: Vec<&'a mut dyn Observer>,
+}
+
+pub struct MatchResult {
+    pub placements: Vec<usize>, // 0 is first, with forfeited players behind everyone else
+    pub record: GameRecord,
+    pub decisions: Vec<TimedDecision>,
+    pub forfeits: Vec<Forfeit>,
+    pub game: GameState,
+}
+
+#[derive(Clone, Debug)]
+pub struct TimedDecision {
+    pub seat: usize,
+    pub play: Play,
+    pub elapsed: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct Forfeit {
+    pub seat: usize,
+    pub reason: String,
+}
+
+impl<'a> Match<'a> {
+    pub fn new(players: Vec<Box<dyn Player>>) -> Match<'a> {
+        let rules = Rules {
+            n_players: players.len(),
+            ..Rules::default()
+        };
+
+        Match {
+            players,
+            rules,
+            deck: None,
+            observers: Vec::new(),
+        }
+    }
+
+    pub fn with_rules(mut self, rules: Rules) -> Match<'a> {
+        self.rules = rules;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Match<'a> {
+        let mut deck = entire_deck();
+        deck.shuffle(&mut StdRng::seed_from_u64(seed));
+        self.deck = Some(deck);
+        self
+    }
+
+    pub fn with_deck(mut self, deck: Vec<Card>) -> Match<'a> {
+        self.deck = Some(deck);
+        self
+    }
+
+    pub fn with_observer(mut self, observer: &'a mut dyn Observer) -> Match<'a> {
+        self.observers.push(observer);
+        self
+    }
+
+    pub fn run(mut self) -> Result<MatchResult, String> {
+        self.rules.check()?;
+        if self.players.len() != self.rules.n_players {
+            return Err(format!(
+                "{} players for {} seats",
+                self.players.len(),
+                self.rules.n_players
+            ));
+        }
+
+        let deck = match self.deck.take() {
+            Some(deck) => deck,
+            None => {
+                let mut deck = entire_deck();
+                deck.shuffle(&mut thread_rng());
+                deck
+            }
+        };
+        let mut sorted = deck.clone();
+        sorted.sort();
+        if sorted != entire_deck() {
+            return Err("the deck needs every card exactly once".to_string());
+        }
+
+        let game = GameState::with_rules(self.rules, deck);
+        let driven = drive(&self.players, game, &mut self.observers);
+
+        Ok(MatchResult {
+            placements: final_placements(&driven.game, &driven.forfeits),
+            record: driven.game.to_record(),
+            decisions: driven.decisions,
+            forfeits: driven.forfeits,
+            game: driven.game,
+        })
+    }
+}
+
+struct Driven {
+    game: GameState,
+    decisions: Vec<TimedDecision>,
+    forfeits: Vec<Forfeit>,
+}
+
+fn drive(
+    players: &[Box<dyn Player>],
+    mut game: GameState,
+    observers: &mut [&mut dyn Observer],
+) -> Driven {
+    let mut decisions = Vec::new();
+    let mut forfeits: Vec<Forfeit> = Vec::new();
+
+    notify(observers, &deal_events(&game));
+
+    while !game.is_over() {
+        let seat = game.current_player;
+
+        let (play, elapsed) = {
+            let interface = SafeGameInterface::from_game(&game);
+            let start = Instant::now();
+
+            let play = if forfeits.iter().any(|f| f.seat == seat) {
+                stand_in(&interface)
+            } else {
+                match ask(&*players[seat], &interface) {
+                    Ok(play) => play,
+                    Err(reason) => {
+                        let forfeit = Forfeit { seat, reason };
+                        notify(
+                            observers,
+                            &[GameEvent::Forfeited {
+                                seat,
+                                reason: forfeit.reason.clone(),
+                            }],
+                        );
+                        forfeits.push(forfeit);
+                        stand_in(&interface)
+                    }
+                }
+            };
+            let elapsed = start.elapsed();
+
+            for observer in observers.iter_mut() {
+                observer.on_decision(&interface, &play);
+            }
+            (play, elapsed)
+        };
+
+        decisions.push(TimedDecision {
+            seat,
+            play: play.clone(),
+            elapsed,
+        });
+
+        let finished_before = game.finished().len();
+        game.play(play.clone());
+
+        // the game doesn't know about forfeits, so the observers are told the placements with them
+        let mut events = play_events(seat, &play, finished_before, &game);
+        for event in events.iter_mut() {
+            if let GameEvent::GameOver { placements } = event {
+                *placements = final_placements(&game, &forfeits);
+            }
+        }
+        notify(observers, &events);
+    }
+
+    Driven {
+        game,
+        decisions,
+        forfeits,
+    }
+}
+
+// where everyone placed in a finished game, with forfeited players at the back
+// in the order they were forfeited (the first one last)
+fn final_placements(game: &GameState, forfeits: &[Forfeit]) -> Vec<usize> {
+    let placements = game.placements();
+
+    let mut order: Vec<usize> = (0..placements.len()).collect();
+    order.sort_by_key(|&seat| {
+        let forfeited = forfeits.iter().position(|f| f.seat == seat);
+        (forfeited.map(|i| usize::MAX - i), placements[seat])
+    });
+
+    let mut final_placements = vec![0; placements.len()];
+    for (place, &seat) in order.iter().enumerate() {
+        final_placements[seat] = place;
+    }
+    final_placements
+}
+
+fn ask(player: &dyn Player, game: &SafeGameInterface) -> Result<Play, String> {
+    let play = panic::catch_unwind(AssertUnwindSafe(|| player.choose_play(game))).map_err(|e| {
+        let message = e
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| e.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "no message".to_string());
+        format!("panicked: {}", message)
+    })?;
+
+    // going back through can_play also makes sure the kind is right
+    game.can_play(play.cards().to_vec())
+        .map_err(|e| format!("made an illegal play, {} ({:?})", play, e))
+}
+
+/// What a forfeited or misbehaving player plays instead
+pub fn stand_in(game: &SafeGameInterface) -> Play {
+    // pass if we can, otherwise the smallest thing we can play
+    game.legal_plays()
+        .into_iter()
+        .min_by(|a, b| {
+            // plays can't be compared with a pass
+            if a.is_pass() || b.is_pass() {
+                return b.is_pass().cmp(&a.is_pass());
+            }
+            a.cards().len().cmp(&b.cards().len()).then_with(|| a.cmp(b))
+        })
+        .expect("there is always a legal play")
+}
+
+fn notify(observers: &mut [&mut dyn Observer], events: &[GameEvent]) {
+    for event in events {
+        for observer in observers.iter_mut() {
+            observer.on_event(event);
+        }
+    }
+}
+
+/// Turns a callback that wants to see every decision into an observer
+pub struct OnDecision<F>(pub F);
+
+impl<F: FnMut(&SafeGameInterface, &Play)> Observer for OnDecision<F> {
+    fn on_event(&mut self, _event: &GameEvent) {}
+
+    fn on_decision(&mut self, game: &SafeGameInterface, play: &Play) {
+        (self.0)(game, play)
+    }
+}
@@ -0,0 +1,760 @@
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+use std::collections::HashMap;
+use std::io::{self, BufReader};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bot::{MachinePlayer, Player};
+use crate::card::{entire_deck, Card};
+use crate::game::{GameState, Observation, Rules, SafeGameInterface};
+use crate::hint;
+use crate::play::Play;
+use crate::protocol::{receive, send, ClientMessage, ServerMessage};
+use crate::train::DEFAULT_PARAMETERS;
+use crate::websocket::{self, Message, Request};
+
+// a multiplayer server for playing over a local network, speaking the json line protocol in `protocol`.
+// every table runs its games on its own thread, and every connection gets a thread that reads from it
+// and another that writes to it, so that a client that stops reading only holds up itself.
+// the remote seats are players like any other, they just wait for their connection to send a play.
+//
+// browsers get the same messages over a websocket at /ws on the web listener,
+// which also serves the client in web/index.html at /
+
+const INDEX: &str = include_str!("../web/index.html");
+
+// how often the waiting threads check whether anything has changed
+const POLL: Duration = Duration::from_millis(100);
+
+// a client that won't take a message for this long is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub type BotFactory = Arc<dyn Fn() -> Box<dyn Player> + Send + Sync>;
+
+pub enum SeatConfig {
+    Remote,
+    Bot(BotFactory),
+}
+
+pub struct TableConfig {
+    pub name: String,
+    pub rules: Rules,
+    pub seats: Vec<SeatConfig>,
+}
+
+pub struct ServerConfig {
+    pub tables: Vec<TableConfig>,
+    pub reconnect_timeout: Duration, // after this long without a connection, a bot plays for the seat
+    pub between_games: Duration,
+}
+
+/// Hosts the tables in `config` on `listener`, and for browsers on `web`, forever
+pub fn serve(
+    listener: TcpListener,
+    web: Option<TcpListener>,
+    config: ServerConfig,
+) -> io::Result<()> {
+    let lobby = Arc::new(Mutex::new(Lobby {
+        tables: HashMap::new(),
+        reconnect_timeout: config.reconnect_timeout,
+    }));
+
+    for table in config.tables {
+        let mut seats = Vec::new();
+        let mut moves = Vec::new();
+
+        for seat in table.seats.iter() {
+            match seat {
+                SeatConfig::Remote => {
+                    let (sender, receiver) = mpsc::channel();
+                    seats.push(Seat::remote(sender));
+                    moves.push(Some(receiver));
+                }
+                SeatConfig::Bot(_) => {
+                    seats.push(Seat::bot());
+                    moves.push(None);
+                }
+            }
+        }
+
+        lobby
+            .lock()
+            .unwrap()
+            .tables
+            .insert(table.name.clone(), Table { seats });
+
+        let lobby = Arc::clone(&lobby);
+        let between_games = config.between_games;
+
+        thread::spawn(move || run_table(lobby, table, moves, between_games));
+    }
+
+    // every connection gets its own id, whichever listener it came in on
+    let next_id = Arc::new(AtomicUsize::new(0));
+
+    if let Some(web) = web {
+        let lobby = Arc::clone(&lobby);
+        let next_id = Arc::clone(&next_id);
+
+        thread::spawn(move || {
+            for stream in web.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let lobby = Arc::clone(&lobby);
+                        let id = next_id.fetch_add(1, Ordering::SeqCst);
+                        thread::spawn(move || handle_web(lobby, stream, id));
+                    }
+                    Err(e) => eprintln!("couldn't accept a connection: {}", e),
+                }
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let lobby = Arc::clone(&lobby);
+                let id = next_id.fetch_add(1, Ordering::SeqCst);
+
+                thread::spawn(move || {
+                    let reader = match stream.try_clone() {
+                        Ok(s) => BufReader::new(s),
+                        Err(_) => return,
+                    };
+                    if let Ok(connection) = Connection::open(id, stream, Transport::Lines) {
+                        handle_connection(lobby, reader, connection)
+                    }
+                });
+            }
+            Err(e) => eprintln!("couldn't accept a connection: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+struct Lobby {
+    tables: HashMap<String, Table>,
+    reconnect_timeout: Duration,
+}
+
+impl Lobby {
+    fn seat(&mut self, table: &str, seat: usize) -> &mut Seat {
+        &mut self.tables.get_mut(table).unwrap().seats[seat]
+    }
+}
+
+struct Table {
+    seats: Vec<Seat>,
+}
+
+impl Table {
+    fn broadcast(&mut self, message: &ServerMessage) {
+        for seat in self.seats.iter_mut() {
+            seat.send(message);
+        }
+    }
+
+    fn missing(&self) -> usize {
+        self.seats
+            .iter()
+            .filter(|s| s.moves.is_some() && s.token.is_none())
+            .count()
+    }
+}
+
+struct Seat {
+    moves: Option<Sender<Vec<Card>>>, // None for bots
+    token: Option<String>,            // set once someone has sat here
+    connection: Option<Connection>,
+    disconnected_at: Option<Instant>,
+    observation: Option<Observation>, // the last state we sent, for anyone who rejoins
+    waiting_on: bool,
+    assist: bool, // send hints and warnings
+}
+
+impl Seat {
+    fn remote(moves: Sender<Vec<Card>>) -> Seat {
+        Seat {
+            moves: Some(moves),
+            token: None,
+            connection: None,
+            disconnected_at: None,
+            observation: None,
+            waiting_on: false,
+            assist: false,
+        }
+    }
+
+    fn bot() -> Seat {
+        Seat {
+            moves: None,
+            token: None,
+            connection: None,
+            disconnected_at: None,
+            observation: None,
+            waiting_on: false,
+            assist: false,
+        }
+    }
+
+    fn send(&mut self, message: &ServerMessage) {
+        let failed = match self.connection {
+            Some(ref connection) => connection.send(message).is_err(),
+            None => false,
+        };
+
+        if failed {
+            self.disconnect();
+        }
+    }
+
+    fn connect(&mut self, connection: Connection) {
+        // whoever was sitting here before gets kicked off
+        if let Some(old) = self.connection.take() {
+            let _ = old.stream.shutdown(Shutdown::Both);
+        }
+        self.connection = Some(connection);
+        self.disconnected_at = None;
+    }
+
+    fn disconnect(&mut self) {
+        self.connection = None;
+        self.disconnected_at = Some(Instant::now());
+    }
+
+    // nobody has sat here, or whoever did has been gone for too long to come back
+    fn is_free(&self, reconnect_timeout: Duration) -> bool {
+        self.moves.is_some()
+            && match (&self.token, self.disconnected_at) {
+                (None, _) => true,
+                (Some(_), Some(at)) => at.elapsed() >= reconnect_timeout,
+                (Some(_), None) => false,
+            }
+    }
+
+    fn is_connected_to(&self, id: usize) -> bool {
+        self.connection.as_ref().map(|c| c.id) == Some(id)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Transport {
+    Lines,
+    WebSocket,
+}
+
+// what the writing thread of a connection has left to write
+enum Outgoing {
+    Message(ServerMessage),
+    Pong(Vec<u8>),
+    Close(u16),
+}
+
+#[derive(Clone)]
+struct Connection {
+    id: usize,
+    stream: Arc<TcpStream>, // only for hanging up, the writing thread has its own
+    transport: Transport,
+    outgoing: Sender<Outgoing>,
+}
+
+impl Connection {
+    // starts the thread that writes to `stream`, which stops when every copy of the connection is gone
+    fn open(id: usize, stream: TcpStream, transport: Transport) -> io::Result<Connection> {
+        let mut writer = stream.try_clone()?;
+        writer.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+        let (outgoing, queue) = mpsc::channel();
+
+        thread::spawn(move || {
+            for message in queue {
+                let written = match (message, transport) {
+                    (Outgoing::Message(message), Transport::Lines) => send(&mut writer, &message),
+                    (Outgoing::Message(message), Transport::WebSocket) => {
+                        serde_json::to_string(&message)
+                            .map_err(io::Error::from)
+                            .and_then(|text| websocket::write_text(&mut writer, &text))
+                    }
+                    (Outgoing::Pong(data), _) => websocket::write_pong(&mut writer, &data),
+                    (Outgoing::Close(code), _) => websocket::write_close(&mut writer, code),
+                };
+
+                // the reading thread sees the hang up, and leaves the seat open for a rejoin
+                if written.is_err() {
+                    let _ = writer.shutdown(Shutdown::Both);
+                    break;
+                }
+            }
+        });
+
+        Ok(Connection {
+            id,
+            stream: Arc::new(stream),
+            transport,
+            outgoing,
+        })
+    }
+
+    // queues the message without waiting for it to be written, and fails once writing has
+    fn send(&self, message: &ServerMessage) -> Result<(), ()> {
+        self.queue(Outgoing::Message(message.clone()))
+    }
+
+    fn queue(&self, outgoing: Outgoing) -> Result<(), ()> {
+        self.outgoing.send(outgoing).map_err(|_| ())
+    }
+}
+
+fn run_table(
+    lobby: Arc<Mutex<Lobby>>,
+    config: TableConfig,
+    moves: Vec<Option<Receiver<Vec<Card>>>>,
+    between_games: Duration,
+) {
+    let name = config.name;
+
+    let players: Vec<Box<dyn Player>> = config
+        .seats
+        .into_iter()
+        .zip(moves)
+        .enumerate()
+        .map(|(seat, (config, moves))| match (config, moves) {
+            (SeatConfig::Bot(make_bot), _) => make_bot(),
+            (SeatConfig::Remote, moves) => Box::new(RemotePlayer {
+                lobby: Arc::clone(&lobby),
+                table: name.clone(),
+                seat,
+                moves: moves.unwrap(),
+                stand_in: MachinePlayer::new(DEFAULT_PARAMETERS),
+            }) as Box<dyn Player>,
+        })
+        .collect();
+
+    for game_number in 0.. {
+        // every remote seat needs someone sitting in it
+        while lobby.lock().unwrap().tables[&name].missing() > 0 {
+            thread::sleep(POLL);
+        }
+
+        let mut deck = entire_deck();
+        deck.shuffle(&mut thread_rng());
+        let mut game = GameState::with_rules(config.rules, deck);
+
+        {
+            let mut lobby = lobby.lock().unwrap();
+            let table = lobby.tables.get_mut(&name).unwrap();
+            for (seat, s) in table.seats.iter_mut().enumerate() {
+                s.send(&ServerMessage::GameStarted {
+                    game: game_number,
+                    observation: game.observation(seat),
+                });
+                s.observation = Some(game.observation(seat));
+            }
+        }
+
+        while !game.is_over() {
+            let seat = game.current_player;
+            let play = players[seat].choose_play(&SafeGameInterface::from_game(&game));
+            game.play(play.clone());
+
+            let mut lobby = lobby.lock().unwrap();
+            let table = lobby.tables.get_mut(&name).unwrap();
+            table.broadcast(&ServerMessage::Played { seat, play });
+
+            for (seat, s) in table.seats.iter_mut().enumerate() {
+                let observation = game.observation(seat);
+                s.send(&ServerMessage::State {
+                    observation: observation.clone(),
+                });
+                s.observation = Some(observation);
+            }
+        }
+
+        lobby
+            .lock()
+            .unwrap()
+            .tables
+            .get_mut(&name)
+            .unwrap()
+            .broadcast(&ServerMessage::GameOver {
+                placements: game.placements(),
+            });
+
+        thread::sleep(between_games);
+    }
+}
+
+/// A seat that is played over the network
+struct RemotePlayer {
+    lobby: Arc<Mutex<Lobby>>,
+    table: String,
+    seat: usize,
+    moves: Receiver<Vec<Card>>,
+    stand_in: MachinePlayer, // plays for the seat when nobody is connected to it
+}
+
+impl Player for RemotePlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        // anything left over from the last turn is stale
+        while self.moves.try_recv().is_ok() {}
+
+        // hints and warnings are worked out without holding the lock, they take a moment
+        let hint = if self.assist() {
+            Some(hint::hint(game))
+        } else {
+            None
+        };
+
+        {
+            let mut lobby = self.lobby.lock().unwrap();
+            let seat = lobby.seat(&self.table, self.seat);
+            seat.waiting_on = true;
+            if let Some(hint) = hint {
+                seat.send(&ServerMessage::Hint { hint });
+            }
+            seat.send(&ServerMessage::YourTurn {
+                observation: game.observation(),
+            });
+        }
+
+        // a play we have warned about, which goes through if it comes again
+        let mut confirming: Option<Play> = None;
+
+        loop {
+            if let Ok(cards) = self.moves.recv_timeout(POLL) {
+                let play = match game.can_play(cards) {
+                    Ok(play) => play,
+                    Err(e) => {
+                        self.send(&ServerMessage::Rejected {
+                            reason: format!("{:?}", e),
+                        });
+                        continue;
+                    }
+                };
+
+                if confirming.as_ref() != Some(&play) && self.assist() {
+                    let warnings = hint::warnings(game, &play);
+                    if !warnings.is_empty() {
+                        self.send(&ServerMessage::Warning { warnings });
+                        confirming = Some(play);
+                        continue;
+                    }
+                }
+
+                self.lobby
+                    .lock()
+                    .unwrap()
+                    .seat(&self.table, self.seat)
+                    .waiting_on = false;
+                return play;
+            }
+
+            let mut lobby = self.lobby.lock().unwrap();
+            let reconnect_timeout = lobby.reconnect_timeout;
+            let seat = lobby.seat(&self.table, self.seat);
+
+            if seat.is_free(reconnect_timeout) {
+                seat.waiting_on = false;
+                drop(lobby);
+                return self.stand_in.choose_play(game);
+            }
+        }
+    }
+}
+
+impl RemotePlayer {
+    fn assist(&self) -> bool {
+        self.lobby
+            .lock()
+            .unwrap()
+            .seat(&self.table, self.seat)
+            .assist
+    }
+
+    fn send(&self, message: &ServerMessage) {
+        self.lobby
+            .lock()
+            .unwrap()
+            .seat(&self.table, self.seat)
+            .send(message);
+    }
+}
+
+fn handle_web(lobby: Arc<Mutex<Lobby>>, stream: TcpStream, id: usize) {
+    // this is only meant for playing on the same machine
+    if !stream.peer_addr().is_ok_and(|a| a.ip().is_loopback()) {
+        return;
+    }
+
+    let mut reader = match stream.try_clone() {
+        Ok(s) => BufReader::new(s),
+        Err(_) => return,
+    };
+    let mut writer = stream;
+
+    let request = match Request::read(&mut reader) {
+        Ok(request) => request,
+        Err(_) => return,
+    };
+
+    // and a page from somewhere else shouldn't be able to take a seat with our browser
+    let local = request.header("origin").is_none_or(is_local_origin);
+
+    let _ = match (request.method.as_str(), request.path.as_str()) {
+        _ if !local => websocket::respond(&mut writer, "403 Forbidden", "text/plain", b"forbidden"),
+        ("GET", "/ws") if request.is_websocket() => {
+            if websocket::accept(&mut writer, &request).is_ok() {
+                if let Ok(connection) = Connection::open(id, writer, Transport::WebSocket) {
+                    handle_connection(lobby, reader, connection);
+                }
+            }
+            return;
+        }
+        ("GET", "/") | ("GET", "/index.html") => websocket::respond(
+            &mut writer,
+            "200 OK",
+            "text/html; charset=utf-8",
+            INDEX.as_bytes(),
+        ),
+        _ => websocket::respond(&mut writer, "404 Not Found", "text/plain", b"not found"),
+    };
+}
+
+fn is_local_origin(origin: &str) -> bool {
+    let host = origin.split_once("://").map_or(origin, |(_, host)| host);
+
+    // take off the port, being careful with ipv6 addresses like [::1]:8080
+    let host = match host.rfind(':') {
+        Some(colon) if !host[colon..].contains(']') => &host[..colon],
+        _ => host,
+    };
+
+    ["localhost", "127.0.0.1", "[::1]"].contains(&host)
+}
+
+enum Incoming {
+    Message(ClientMessage),
+    Garbled(String),
+    Closed,
+}
+
+fn read_incoming(reader: &mut BufReader<TcpStream>, connection: &Connection) -> Incoming {
+    match connection.transport {
+        Transport::Lines => match receive::<ClientMessage>(reader) {
+            Ok(Some(message)) => Incoming::Message(message),
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData => {
+                Incoming::Garbled(e.to_string())
+            }
+            _ => Incoming::Closed,
+        },
+        Transport::WebSocket => {
+            let pong = |data| {
+                connection
+                    .queue(Outgoing::Pong(data))
+                    .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+            };
+            match websocket::read_message(reader, pong) {
+                Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                    Ok(message) => Incoming::Message(message),
+                    Err(e) => Incoming::Garbled(e.to_string()),
+                },
+                Ok(Message::Binary) => {
+                    let _ = connection.queue(Outgoing::Close(websocket::UNSUPPORTED_DATA));
+                    Incoming::Closed
+                }
+                Ok(Message::Close) => {
+                    let _ = connection.queue(Outgoing::Close(websocket::NORMAL_CLOSURE));
+                    Incoming::Closed
+                }
+                Err(_) => Incoming::Closed,
+            }
+        }
+    }
+}
+
+fn handle_connection(
+    lobby: Arc<Mutex<Lobby>>,
+    mut reader: BufReader<TcpStream>,
+    connection: Connection,
+) {
+    let id = connection.id;
+
+    // the table and seat this connection is sitting in
+    let mut seated: Option<(String, usize)> = None;
+
+    loop {
+        let message = match read_incoming(&mut reader, &connection) {
+            Incoming::Message(message) => message,
+            Incoming::Garbled(e) => {
+                let message = ServerMessage::Error {
+                    message: format!("couldn't understand that: {}", e),
+                };
+                if connection.send(&message).is_err() {
+                    break;
+                }
+                continue;
+            }
+            Incoming::Closed => break,
+        };
+
+        let mut lobby = lobby.lock().unwrap();
+
+        let reply = match message {
+            ClientMessage::Join { .. } | ClientMessage::Rejoin { .. } if seated.is_some() => {
+                Err("you are already sitting at a table".to_string())
+            }
+            ClientMessage::Join { table, seat } => {
+                join(&mut lobby, &table, seat, &connection).map(|seat| {
+                    seated = Some((table, seat));
+                })
+            }
+            ClientMessage::Rejoin { table, token } => {
+                rejoin(&mut lobby, &table, &token, &connection).map(|seat| {
+                    seated = Some((table, seat));
+                })
+            }
+            ClientMessage::Play { cards } => submit(&mut lobby, &seated, id, cards),
+            ClientMessage::Pass => submit(&mut lobby, &seated, id, vec![]),
+            ClientMessage::Assist { on } => match seated {
+                Some((ref table, seat)) => {
+                    lobby.seat(table, seat).assist = on;
+                    Ok(())
+                }
+                None => Err("join a table first".to_string()),
+            },
+        };
+
+        if let Err(message) = reply {
+            if connection.send(&ServerMessage::Error { message }).is_err() {
+                break;
+            }
+        }
+    }
+
+    // leave the seat open for a rejoin
+    if let Some((table, seat)) = seated {
+        let mut lobby = lobby.lock().unwrap();
+        let seat = lobby.seat(&table, seat);
+        if seat.is_connected_to(id) {
+            seat.disconnect();
+        }
+    }
+}
+
+fn join(
+    lobby: &mut Lobby,
+    table_name: &str,
+    seat: Option<usize>,
+    connection: &Connection,
+) -> Result<usize, String> {
+    let table = lobby
+        .tables
+        .get_mut(table_name)
+        .ok_or_else(|| format!("there is no table called {}", table_name))?;
+
+    let reconnect_timeout = lobby.reconnect_timeout;
+    let free = |s: &Seat| s.is_free(reconnect_timeout);
+
+    let seat = match seat {
+        Some(seat) if seat >= table.seats.len() => {
+            return Err(format!("there is no seat {}", seat))
+        }
+        Some(seat) if !free(&table.seats[seat]) => return Err(format!("seat {} isn't free", seat)),
+        Some(seat) => seat,
+        None => table
+            .seats
+            .iter()
+            .position(free)
+            .ok_or_else(|| format!("{} is full", table_name))?,
+    };
+
+    let token = format!("{:016x}", thread_rng().gen::<u64>());
+
+    let s = &mut table.seats[seat];
+    s.token = Some(token.clone());
+    s.assist = false;
+    s.connect(connection.clone());
+    s.send(&ServerMessage::Welcome {
+        table: table_name.to_string(),
+        seat,
+        token,
+    });
+
+    let missing = table.missing();
+    if missing > 0 {
+        table.broadcast(&ServerMessage::Waiting { missing });
+    }
+
+    Ok(seat)
+}
+
+fn rejoin(
+    lobby: &mut Lobby,
+    table_name: &str,
+    token: &str,
+    connection: &Connection,
+) -> Result<usize, String> {
+    let table = lobby
+        .tables
+        .get_mut(table_name)
+        .ok_or_else(|| format!("there is no table called {}", table_name))?;
+
+    let seat = table
+        .seats
+        .iter()
+        .position(|s| s.token.as_deref() == Some(token))
+        .ok_or_else(|| "that token doesn't belong to any seat here".to_string())?;
+
+    let s = &mut table.seats[seat];
+
+    s.connect(connection.clone());
+
+    s.send(&ServerMessage::Welcome {
+        table: table_name.to_string(),
+        seat,
+        token: token.to_string(),
+    });
+
+    if let Some(observation) = s.observation.clone() {
+        s.send(&ServerMessage::State {
+            observation: observation.clone(),
+        });
+
+        if s.waiting_on {
+            s.send(&ServerMessage::YourTurn { observation });
+        }
+    }
+
+    Ok(seat)
+}
+
+fn submit(
+    lobby: &mut Lobby,
+    seated: &Option<(String, usize)>,
+    id: usize,
+    cards: Vec<Card>,
+) -> Result<(), String> {
+    let (table, seat) = seated
+        .as_ref()
+        .ok_or_else(|| "join a table first".to_string())?;
+    let seat = lobby.seat(table, *seat);
+
+    if !seat.is_connected_to(id) {
+        Err("someone else has taken your seat".to_string())
+    } else if !seat.waiting_on {
+        Err("it isn't your turn".to_string())
+    } else {
+        seat.moves
+            .as_ref()
+            .unwrap()
+            .send(cards)
+            .map_err(|_| "this table has stopped".to_string())
+    }
+}
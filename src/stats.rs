@@ -0,0 +1,250 @@
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::card::{Rank, ALL_RANKS};
+use crate::events::{record_events, GameEvent, Observer};
+use crate::game::{GameError, GameRecord};
+use crate::play::{Play, PlayKind};
+use crate::strength::hand_strength;
+
+// numbers about how games actually go, collected from saved games or from games as they are played.
+// these are the "numbers from research" that `bot::cost` wants.
+// a trick is everything from a lead until everyone passes on the last play of it (or the game ends)
+
+#[derive(Default)]
+pub struct Stats {
+    games: usize,
+    turns: usize,                     // plays and passes, over every finished game
+    kinds: BTreeMap<PlayKind, usize>, // passes included
+
+    // for each rank, how many times a play ranked by it was made, and how many tricks it won
+    rank_played: [usize; 13],
+    rank_won_trick: [usize; 13],
+
+    // singles and pairs that were on top of a trick, and how many of them got beaten
+    on_top: [[usize; 13]; 2],
+    beaten: [[usize; 13]; 2],
+
+    control_gaps: Vec<usize>, // turns between a seat having control and getting it back
+    first_player_wins: usize,
+    seat_wins: Vec<usize>,
+    by_min_plays: BTreeMap<usize, (usize, usize)>, // hands dealt that took this few plays, and how many of them won
+
+    // the game that is being watched right now
+    turn: usize,
+    top: Option<Play>,
+    last_control: Vec<Option<usize>>,
+    first: usize,
+    min_plays: Vec<Option<usize>>, // for each hand that was dealt, unless it was too big to split up
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    pub fn add_record(&mut self, record: &GameRecord) -> Result<(), GameError> {
+        for event in record_events(record)? {
+            self.on_event(&event);
+        }
+        Ok(())
+    }
+
+    fn gain_control(&mut self, seat: usize) {
+        if let Some(since) = self.last_control[seat] {
+            self.control_gaps.push(self.turn - since);
+        }
+        self.last_control[seat] = Some(self.turn);
+    }
+
+    fn end_trick(&mut self) {
+        // whatever is on top has won the trick
+        if let Some(top) = self.top.take() {
+            let rank = top.ranking_card().unwrap().rank as usize;
+            self.rank_won_trick[rank] += 1;
+        }
+    }
+
+    pub fn summary(&self) -> Summary {
+        let ratio = |a: usize, b: usize| {
+            if b == 0 {
+                None
+            } else {
+                Some(a as f64 / b as f64)
+            }
+        };
+
+        let n_plays: usize = self.kinds.values().sum();
+
+        let by_rank = |counts: &[usize; 13], out_of: &[usize; 13]| -> BTreeMap<Rank, f64> {
+            ALL_RANKS
+                .iter()
+                .filter_map(|&rank| {
+                    let i = rank as usize;
+                    ratio(counts[i], out_of[i]).map(|r| (rank, r))
+                })
+                .collect()
+        };
+
+        Summary {
+            games: self.games,
+            average_length: ratio(self.turns, self.games),
+            kind_frequency: self
+                .kinds
+                .iter()
+                .map(|(&kind, &n)| (kind, n as f64 / n_plays as f64))
+                .collect(),
+            rank_trick_win_rate: by_rank(&self.rank_won_trick, &self.rank_played),
+            single_beaten: by_rank(&self.beaten[0], &self.on_top[0]),
+            pair_beaten: by_rank(&self.beaten[1], &self.on_top[1]),
+            control_return_turns: ratio(self.control_gaps.iter().sum(), self.control_gaps.len()),
+            first_player_win_rate: ratio(self.first_player_wins, self.games),
+            seat_win_rate: self
+                .seat_wins
+                .iter()
+                .filter_map(|&wins| ratio(wins, self.games))
+                .collect(),
+            min_plays_win_rate: self
+                .by_min_plays
+                .iter()
+                .map(|(&plays, &(hands, wins))| (plays, wins as f64 / hands as f64))
+                .collect(),
+        }
+    }
+}
+
+impl Observer for Stats {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Dealt { hands, first } => {
+                if self.seat_wins.len() < hands.len() {
+                    self.seat_wins.resize(hands.len(), 0);
+                }
+                self.turn = 0;
+                self.top = None;
+                self.last_control = vec![None; hands.len()];
+                self.first = *first;
+                self.last_control[*first] = Some(0);
+                self.min_plays = hands
+                    .iter()
+                    .map(|h| hand_strength(h).ok().map(|e| e.min_plays))
+                    .collect();
+            }
+            GameEvent::Played { play, .. } => {
+                self.turn += 1;
+                *self.kinds.entry(play.kind()).or_insert(0) += 1;
+                self.rank_played[play.ranking_card().unwrap().rank as usize] += 1;
+
+                if let Some(top) = self.top.replace(play.clone()) {
+                    if let Some(small) = small_index(&top) {
+                        self.beaten[small][top.ranking_card().unwrap().rank as usize] += 1;
+                    }
+                }
+                if let Some(small) = small_index(play) {
+                    self.on_top[small][play.ranking_card().unwrap().rank as usize] += 1;
+                }
+            }
+            GameEvent::Passed { .. } => {
+                self.turn += 1;
+                *self.kinds.entry(PlayKind::Pass).or_insert(0) += 1;
+            }
+            GameEvent::TrickCleared => self.end_trick(),
+            GameEvent::ControlGained { seat } => self.gain_control(*seat),
+            GameEvent::PlayerFinished { .. } | GameEvent::Forfeited { .. } => {}
+            GameEvent::GameOver { placements } => {
+                // the last play of the game ends its trick
+                self.end_trick();
+
+                self.games += 1;
+                self.turns += self.turn;
+                for (seat, &place) in placements.iter().enumerate() {
+                    // hands too big to split up don't count towards these
+                    if let Some(min_plays) = self.min_plays[seat] {
+                        let dealt = self.by_min_plays.entry(min_plays).or_insert((0, 0));
+                        dealt.0 += 1;
+                        if place == 0 {
+                            dealt.1 += 1;
+                        }
+                    }
+                    if place == 0 {
+                        self.seat_wins[seat] += 1;
+                        if seat == self.first {
+                            self.first_player_wins += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// which row of `on_top` and `beaten` a play goes in
+fn small_index(play: &Play) -> Option<usize> {
+    match play.kind() {
+        PlayKind::Single => Some(0),
+        PlayKind::Pair => Some(1),
+        _ => None,
+    }
+}
+
+/// What `Stats` found, ready to be written out. Anything there wasn't enough data for is left out
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub games: usize,
+    pub average_length: Option<f64>, // in plays and passes
+    pub kind_frequency: BTreeMap<PlayKind, f64>, // out of every play and pass
+    pub rank_trick_win_rate: BTreeMap<Rank, f64>, // of the plays ranked by each rank, how many won their trick
+    pub single_beaten: BTreeMap<Rank, f64>, // of the singles on top of a trick, how many got beaten
+    pub pair_beaten: BTreeMap<Rank, f64>,
+    pub control_return_turns: Option<f64>, // from a seat having control to it getting it back
+    pub first_player_win_rate: Option<f64>, // the player with the 3♣
+    pub seat_win_rate: Vec<f64>,
+    pub min_plays_win_rate: BTreeMap<usize, f64>, // by the fewest plays the dealt hand could be played out in
+}
+
+impl Summary {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// One row for each number, as statistic,key,value
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("statistic,key,value\n");
+        let mut row = |statistic: &str, key: &str, value: f64| {
+            let _ = writeln!(csv, "{},{},{}", statistic, key, value);
+        };
+
+        row("games", "", self.games as f64);
+        if let Some(length) = self.average_length {
+            row("average_length", "", length);
+        }
+        for (kind, frequency) in self.kind_frequency.iter() {
+            row("kind_frequency", &format!("{:?}", kind), *frequency);
+        }
+        for (rank, rate) in self.rank_trick_win_rate.iter() {
+            row("rank_trick_win_rate", &rank.to_string(), *rate);
+        }
+        for (rank, rate) in self.single_beaten.iter() {
+            row("single_beaten", &rank.to_string(), *rate);
+        }
+        for (rank, rate) in self.pair_beaten.iter() {
+            row("pair_beaten", &rank.to_string(), *rate);
+        }
+        if let Some(turns) = self.control_return_turns {
+            row("control_return_turns", "", turns);
+        }
+        if let Some(rate) = self.first_player_win_rate {
+            row("first_player_win_rate", "", rate);
+        }
+        for (seat, rate) in self.seat_win_rate.iter().enumerate() {
+            row("seat_win_rate", &seat.to_string(), *rate);
+        }
+        for (plays, rate) in self.min_plays_win_rate.iter() {
+            row("min_plays_win_rate", &plays.to_string(), *rate);
+        }
+
+        csv
+    }
+}
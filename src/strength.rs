@@ -0,0 +1,215 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use std::fmt;
+
+use crate::bot::Player;
+use crate::card::{entire_deck, Card, Rank};
+use crate::control::{ControlTable, Situation};
+use crate::game::{GameState, Rules};
+use crate::partition::{best_partitions, Objective};
+use crate::play::finder::Finder;
+use crate::play::Play;
+use crate::runner::run_game;
+
+// how good a hand looks before a single card has been played, for looking over deals, for the stats,
+// and for deciding how to open. a hand is better the fewer plays it takes to get rid of it,
+// the more of those plays tend to win their tricks, and the fewer low cards are stuck on their own
+
+/// Weights for the features of a hand (after a constant), fitted to 1200 games of four player self-play
+/// with `pusoy train --mode strength --games 1200 --seed 1 --bots "mcts:iters=200,seed=1"`.
+/// The chance to win is the logistic of the weighted sum. The players have to be good enough
+/// for their results to depend on their hands (machine bots against each other aren't)
+pub const WIN_MODEL: [f64; 4] = [-0.074, -0.239, 0.763, -0.184];
+
+// cards below this rank are low
+const LOW: Rank = Rank::Nine;
+
+// which way each feature has to push the chance to win for a fit to make sense: more plays is worse,
+// more plays that win their tricks is better, and more low cards on their own is worse
+const FEATURES: [(&str, f64); 3] = [
+    ("plays", -1.0),
+    ("plays likely to win their tricks", 1.0),
+    ("low cards on their own", -1.0),
+];
+
+#[derive(Debug, Serialize)]
+pub struct HandEvaluation {
+    pub min_plays: usize,     // the fewest plays that get rid of the whole hand
+    pub partition: Vec<Play>, // a way of doing it, with as many likely control plays as there can be
+    pub control_plays: usize, // plays in the partition that are more likely than not to win their trick
+    pub isolated_low_singles: usize, // low cards that can't be played with anything else in the hand
+    pub win_probability: f64,        // for a freshly dealt hand in a four player game
+}
+
+/// How good `hand` looks, unless it is too big to split up into plays (see `partition::MAX_CARDS`)
+pub fn hand_strength(hand: &[Card]) -> Result<HandEvaluation, String> {
+    let control = ControlTable::built_in();
+
+    let mut cards = hand.to_vec();
+    cards.sort();
+    let plays = Finder::new(cards.clone()).all_plays();
+
+    let isolated_low_singles = cards
+        .iter()
+        .filter(|card| card.rank < LOW)
+        .filter(|card| {
+            !plays
+                .iter()
+                .any(|p| p.cards().len() > 1 && p.cards().contains(card))
+        })
+        .count();
+
+    let partition = best_partitions(&cards, 1, Objective::FewestPlays)?
+        .remove(0)
+        .plays;
+    let control_plays = partition
+        .iter()
+        .filter(|play| control.probability(play, Situation::default()) > 0.5)
+        .count();
+
+    let features = [partition.len(), control_plays, isolated_low_singles];
+
+    Ok(HandEvaluation {
+        min_plays: partition.len(),
+        partition,
+        control_plays,
+        isolated_low_singles,
+        win_probability: win_probability(&WIN_MODEL, features),
+    })
+}
+
+fn win_probability(model: &[f64; 4], features: [usize; 3]) -> f64 {
+    let z = model[0]
+        + features
+            .iter()
+            .zip(&model[1..])
+            .map(|(&x, w)| x as f64 * w)
+            .sum::<f64>();
+    1.0 / (1.0 + (-z).exp())
+}
+
+/// Fits `WIN_MODEL` to `n_games` games between the players that `make_players` makes,
+/// by logistic regression of whether each dealt hand went out first.
+/// Players whose results hardly depend on their hands give fits that make no sense, which are refused
+pub fn fit_win_model(
+    rules: Rules,
+    n_games: usize,
+    seed: u64,
+    make_players: impl Fn() -> Vec<Box<dyn Player>> + Sync,
+) -> Result<[f64; 4], String> {
+    let samples: Vec<([usize; 3], bool)> = (0..n_games)
+        .into_par_iter()
+        .map(|g| {
+            let mut deck = entire_deck();
+            deck.shuffle(&mut StdRng::seed_from_u64(seed.wrapping_add(g as u64)));
+
+            let game = run_game(&make_players(), GameState::with_rules(rules, deck), &mut []);
+            let placements = game.placements();
+
+            // hands too big to split up (with fewer players) are left out
+            game.dealt()
+                .iter()
+                .zip(placements)
+                .filter_map(|(hand, place)| {
+                    let evaluation = hand_strength(hand).ok()?;
+                    let features = [
+                        evaluation.min_plays,
+                        evaluation.control_plays,
+                        evaluation.isolated_low_singles,
+                    ];
+                    Some((features, place == 0))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .flatten()
+        .collect();
+
+    // newton's method on the log loss, which only takes a few steps for this few weights
+    let mut model = [0.0; 4];
+    for _ in 0..25 {
+        let mut gradient = [0.0; 4];
+        let mut hessian = [[0.0; 4]; 4];
+        for &(features, won) in samples.iter() {
+            let p = win_probability(&model, features);
+            let x = [
+                1.0,
+                features[0] as f64,
+                features[1] as f64,
+                features[2] as f64,
+            ];
+            for i in 0..4 {
+                gradient[i] += (p - won as u8 as f64) * x[i];
+                for j in 0..4 {
+                    hessian[i][j] += p * (1.0 - p) * x[i] * x[j];
+                }
+            }
+        }
+
+        let step = solve(hessian, gradient);
+        for (w, d) in model.iter_mut().zip(step.iter()) {
+            *w -= d;
+        }
+    }
+
+    for (&(feature, sign), &weight) in FEATURES.iter().zip(&model[1..]) {
+        if weight * sign < 0.0 {
+            return Err(format!(
+                "the fit says that more {} is {} ({:?}), so these games don't say much about hands",
+                feature,
+                if sign < 0.0 { "better" } else { "worse" },
+                model
+            ));
+        }
+    }
+
+    Ok(model)
+}
+
+// solves a x = b by gaussian elimination
+fn solve(mut a: [[f64; 4]; 4], mut b: [f64; 4]) -> [f64; 4] {
+    for col in 0..4 {
+        let pivot = (col..4)
+            .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+            .unwrap();
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        for row in col + 1..4 {
+            let factor = a[row][col] / a[col][col];
+            let above = a[col];
+            for (x, y) in a[row][col..].iter_mut().zip(&above[col..]) {
+                *x -= factor * y;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = [0.0; 4];
+    for row in (0..4).rev() {
+        let rest: f64 = (row + 1..4).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - rest) / a[row][row];
+    }
+    x
+}
+
+impl fmt::Display for HandEvaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "plays to get rid of it: {}", self.min_plays)?;
+        for play in self.partition.iter() {
+            writeln!(f, "    {}", play)?;
+        }
+        writeln!(f, "likely to win their tricks: {}", self.control_plays)?;
+        writeln!(f, "low cards on their own: {}", self.isolated_low_singles)?;
+        writeln!(
+            f,
+            "chance to go out first: {:.0}%",
+            self.win_probability * 100.0
+        )
+    }
+}
@@ -0,0 +1,412 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::card::{entire_deck, Card};
+use crate::game::SafeGameInterface;
+use crate::play::finder::Finder;
+use crate::play::{Play, PlayKind};
+
+// how likely it is that someone can beat a play, from the cards we can't see and how many each opponent holds.
+// every way of dealing the unseen cards out is taken to be as likely as every other.
+// singles and pairs only depend on how the cards of each rank are split up, so they are counted exactly.
+// five card plays depend on too much at once, so for those we deal hypothetical hands and look in them with `Finder`
+
+/// How many deals to look at for five card plays, unless told otherwise
+pub const DEFAULT_SAMPLES: usize = 2000;
+
+pub struct Threats {
+    unseen: Vec<Card>,
+    opponents: Vec<(usize, usize)>, // seat and hand size
+}
+
+#[derive(Debug, Serialize)]
+pub struct Threat {
+    pub play: Play,
+    pub anyone: f64,                // that at least one opponent can beat it
+    pub by_seat: Vec<(usize, f64)>, // that this opponent can
+    pub samples: Option<usize>,     // deals looked at, or None when it was worked out exactly
+}
+
+impl Threats {
+    /// Cards left over after dealing the opponents their hands are out of the game
+    pub fn new(unseen: Vec<Card>, opponents: Vec<(usize, usize)>) -> Result<Threats, String> {
+        let dealt: usize = opponents.iter().map(|&(_, size)| size).sum();
+        if dealt > unseen.len() {
+            return Err(format!(
+                "the opponents hold {} cards, but only {} are unseen",
+                dealt,
+                unseen.len()
+            ));
+        }
+
+        Ok(Threats { unseen, opponents })
+    }
+
+    /// Everything the player to move can't see, split between the others who are still in the game
+    pub fn from_game(game: &SafeGameInterface) -> Threats {
+        let me = game.my_seat();
+        let seen: Vec<Card> = game
+            .my_hand()
+            .iter()
+            .cloned()
+            .chain(game.played_cards())
+            .collect();
+        let unseen = entire_deck()
+            .into_iter()
+            .filter(|c| !seen.contains(c))
+            .collect();
+
+        let opponents = game
+            .hand_sizes()
+            .into_iter()
+            .enumerate()
+            .filter(|&(seat, size)| seat != me && size > 0)
+            .collect();
+
+        Threats { unseen, opponents }
+    }
+
+    pub fn unseen(&self) -> &[Card] {
+        &self.unseen
+    }
+
+    /// Exactly for singles and pairs, and from `samples` deals for everything else
+    pub fn probability(&self, play: &Play, samples: usize, rng: &mut impl Rng) -> Threat {
+        self.exact(play)
+            .unwrap_or_else(|| self.sampled(play, samples, rng))
+    }
+
+    /// Only singles and pairs can be worked out exactly
+    pub fn exact(&self, play: &Play) -> Option<Threat> {
+        let need = match play.kind() {
+            PlayKind::Single => 1,
+            PlayKind::Pair => 2,
+            _ => return None,
+        };
+
+        let sizes: Vec<usize> = self.opponents.iter().map(|&(_, size)| size).collect();
+        let anyone = 1.0 - nobody_beats(&self.unseen, &sizes, play, need);
+        let by_seat = self
+            .opponents
+            .iter()
+            .map(|&(seat, size)| (seat, 1.0 - nobody_beats(&self.unseen, &[size], play, need)))
+            .collect();
+
+        Some(Threat {
+            play: play.clone(),
+            anyone,
+            by_seat,
+            samples: None,
+        })
+    }
+
+    pub fn sampled(&self, play: &Play, samples: usize, rng: &mut impl Rng) -> Threat {
+        let mut anyone = 0;
+        let mut by_seat = vec![0; self.opponents.len()];
+        let mut deck = self.unseen.clone();
+
+        for _ in 0..samples {
+            deck.shuffle(rng);
+
+            let mut someone = false;
+            let mut rest = &deck[..];
+            for (i, &(_, size)) in self.opponents.iter().enumerate() {
+                let (hand, after) = rest.split_at(size);
+                rest = after;
+
+                if can_beat(hand, play) {
+                    by_seat[i] += 1;
+                    someone = true;
+                }
+            }
+
+            if someone {
+                anyone += 1;
+            }
+        }
+
+        let fraction = |n: usize| n as f64 / samples.max(1) as f64;
+
+        Threat {
+            play: play.clone(),
+            anyone: fraction(anyone),
+            by_seat: self
+                .opponents
+                .iter()
+                .zip(by_seat)
+                .map(|(&(seat, _), n)| (seat, fraction(n)))
+                .collect(),
+            samples: Some(samples),
+        }
+    }
+}
+
+/// Whether any play in `hand` beats `play`
+pub fn can_beat(hand: &[Card], play: &Play) -> bool {
+    let finder = Finder::new(hand.to_vec());
+
+    let beats = |plays: Vec<Play>| plays.iter().any(|p| p.can_play_on(play));
+
+    match play.kind() {
+        PlayKind::Pass => false,
+        PlayKind::Single => beats(finder.singles()),
+        PlayKind::Pair => beats(finder.pairs()),
+        kind => {
+            // any five card play of a higher kind will do, so start with the rarest
+            hand.len() >= 5
+                && (beats(finder.strait_flushes())
+                    || kind <= PlayKind::FourOfAKind && beats(finder.four_of_a_kinds())
+                    || kind <= PlayKind::FullHouse && beats(finder.full_houses())
+                    || kind <= PlayKind::Flush && beats(finder.flushes())
+                    || kind <= PlayKind::Strait && beats(finder.straits()))
+        }
+    }
+}
+
+// the chance that none of the hands, dealt with `sizes` cards from `unseen` (the rest going to nobody),
+// has `need` cards of one rank with the highest above the ranking card of `play`.
+// we count the deals where that's so: the cards of each rank that could make such a set are given out
+// one rank at a time, keeping track of how much room every hand has left, and the other cards can go anywhere
+fn nobody_beats(unseen: &[Card], sizes: &[usize], play: &Play, need: usize) -> f64 {
+    let top = play.ranking_card().unwrap();
+
+    let mut ranks: [Vec<Card>; 13] = Default::default();
+    for &card in unseen {
+        ranks[card.rank as usize].push(card);
+    }
+
+    let mut room = sizes.to_vec();
+    room.push(unseen.len() - sizes.iter().sum::<usize>()); // nobody
+    let buckets = room.len();
+
+    let mut ways: HashMap<Vec<usize>, f64> = HashMap::new();
+    ways.insert(room.clone(), 1.0);
+    let mut free = unseen.len();
+
+    for rank in ranks.iter().filter(|r| r.iter().any(|&c| c > top)) {
+        free -= rank.len();
+
+        let mut next = HashMap::new();
+        for (room, &w) in ways.iter() {
+            // every way of giving each card of this rank to one of the buckets
+            'deal: for code in 0..buckets.pow(rank.len() as u32) {
+                let mut held: Vec<Vec<Card>> = vec![Vec::new(); buckets];
+                let mut code = code;
+                for &card in rank.iter() {
+                    held[code % buckets].push(card);
+                    code /= buckets;
+                }
+
+                let mut after = room.clone();
+                for (bucket, cards) in held.iter().enumerate() {
+                    if cards.len() > after[bucket] {
+                        continue 'deal;
+                    }
+                    after[bucket] -= cards.len();
+
+                    let is_hand = bucket < sizes.len();
+                    if is_hand && cards.len() >= need && cards.iter().any(|&c| c > top) {
+                        continue 'deal;
+                    }
+                }
+
+                *next.entry(after).or_insert(0.0) += w;
+            }
+        }
+        ways = next;
+    }
+
+    let safe: f64 = ways
+        .iter()
+        .map(|(room, &w)| w * multinomial(free, room))
+        .sum();
+    safe / multinomial(unseen.len(), &room)
+}
+
+// the number of ways to split `n` distinct things into groups of the sizes in `groups`
+fn multinomial(n: usize, groups: &[usize]) -> f64 {
+    groups
+        .iter()
+        .fold(factorial(n), |ways, &k| ways / factorial(k))
+}
+
+fn factorial(n: usize) -> f64 {
+    (1..=n).map(|i| i as f64).product()
+}
+
+impl fmt::Display for Threat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:.1}% that someone can beat it",
+            self.play,
+            self.anyone * 100.0
+        )?;
+
+        let seats: Vec<String> = self
+            .by_seat
+            .iter()
+            .map(|(seat, p)| format!("seat {} {:.1}%", seat, p * 100.0))
+            .collect();
+        write!(f, " ({})", seats.join(", "))?;
+
+        match self.samples {
+            Some(samples) => write!(f, ", from {} deals", samples),
+            None => write!(f, ", exactly"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::parse_cards;
+
+    struct Spot {
+        unseen: &'static str,
+        sizes: &'static [usize], // of the opponents, in seats 1, 2, ...
+        play: &'static str,
+    }
+
+    // singles and pairs from real games, with few enough unseen cards to try every deal
+    const SPOTS: [Spot; 5] = [
+        Spot {
+            unseen: "2S KH 4D 5C",
+            sizes: &[2, 2],
+            play: "AS",
+        },
+        Spot {
+            unseen: "KS KH 4D 5C",
+            sizes: &[2, 2],
+            play: "QS QH",
+        },
+        Spot {
+            unseen: "KS KH AD 4D 5C 2C 9H",
+            sizes: &[3, 2],
+            play: "JS JH",
+        },
+        Spot {
+            unseen: "2D 2H 3C 7S 7D 7H QS",
+            sizes: &[2, 3, 1],
+            play: "6C 6S",
+        },
+        Spot {
+            unseen: "AC 3H 4H 5S 6D KS 8C",
+            sizes: &[1, 3],
+            play: "KD",
+        },
+    ];
+
+    fn set_up(spot: &Spot) -> (Threats, Play) {
+        let unseen = parse_cards(spot.unseen).unwrap();
+        let opponents = spot
+            .sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &size)| (i + 1, size))
+            .collect();
+        let play = Play::infer_from_cards(parse_cards(spot.play).unwrap()).unwrap();
+        (Threats::new(unseen, opponents).unwrap(), play)
+    }
+
+    // (anyone, by seat) from every way of dealing the unseen cards, the rest going to nobody
+    fn every_deal(spot: &Spot, play: &Play) -> (f64, Vec<f64>) {
+        let unseen = parse_cards(spot.unseen).unwrap();
+        let buckets = spot.sizes.len() + 1;
+
+        let (mut deals, mut anyone, mut by_seat) = (0, 0, vec![0; spot.sizes.len()]);
+        for code in 0..buckets.pow(unseen.len() as u32) {
+            let mut hands = vec![Vec::new(); buckets];
+            let mut code = code;
+            for &card in unseen.iter() {
+                hands[code % buckets].push(card);
+                code /= buckets;
+            }
+            if hands
+                .iter()
+                .zip(spot.sizes)
+                .any(|(hand, &size)| hand.len() != size)
+            {
+                continue;
+            }
+
+            deals += 1;
+            let beats: Vec<bool> = spot
+                .sizes
+                .iter()
+                .enumerate()
+                .map(|(i, _)| can_beat(&hands[i], play))
+                .collect();
+            if beats.contains(&true) {
+                anyone += 1;
+            }
+            for (n, &beat) in by_seat.iter_mut().zip(beats.iter()) {
+                *n += beat as usize;
+            }
+        }
+
+        let fraction = |n: usize| n as f64 / deals as f64;
+        (
+            fraction(anyone),
+            by_seat.into_iter().map(fraction).collect(),
+        )
+    }
+
+    #[test]
+    fn exact_counts_match_every_deal() {
+        for spot in SPOTS.iter() {
+            let (threats, play) = set_up(spot);
+            let threat = threats.exact(&play).unwrap();
+            let (anyone, by_seat) = every_deal(spot, &play);
+
+            assert_eq!(threat.samples, None);
+            assert!(
+                (threat.anyone - anyone).abs() < 1e-9,
+                "{} in {}",
+                spot.play,
+                spot.unseen
+            );
+            for (&(seat, p), expected) in threat.by_seat.iter().zip(by_seat) {
+                assert!(
+                    (p - expected).abs() < 1e-9,
+                    "seat {} for {}",
+                    seat,
+                    spot.play
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn exact_counts_by_hand() {
+        // whoever has the 2♠ beats the ace, and someone has to
+        let (threats, play) = set_up(&SPOTS[0]);
+        let threat = threats.exact(&play).unwrap();
+        assert!((threat.anyone - 1.0).abs() < 1e-9);
+        assert!(threat.by_seat.iter().all(|&(_, p)| (p - 0.5).abs() < 1e-9));
+
+        // of the 6 ways to split four cards in two, one gives each seat both kings
+        let (threats, play) = set_up(&SPOTS[1]);
+        let threat = threats.exact(&play).unwrap();
+        assert!((threat.anyone - 2.0 / 6.0).abs() < 1e-9);
+        assert!(threat
+            .by_seat
+            .iter()
+            .all(|&(_, p)| (p - 1.0 / 6.0).abs() < 1e-9));
+    }
+
+    #[test]
+    fn only_singles_and_pairs_are_exact() {
+        let unseen = parse_cards("3D 4D 5D 6D 7D 8S 9S").unwrap();
+        let threats = Threats::new(unseen, vec![(1, 5)]).unwrap();
+        let strait = Play::infer_from_cards(parse_cards("3C 4C 5S 6H 7H").unwrap()).unwrap();
+        assert!(threats.exact(&strait).is_none());
+
+        let too_many = Threats::new(parse_cards("3D 4D").unwrap(), vec![(1, 2), (2, 1)]);
+        assert!(too_many.is_err());
+    }
+}
@@ -4,12 +4,16 @@ use rand::seq::SliceRandom;
 
 use std::thread;
 use std::sync::{Arc, Mutex};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
 
 use crate::bot::N_PARAMETERS;
 use crate::bot::{Player, MachinePlayer, cost};
-use crate::game::{SafeGameInterface, GameState};
+use crate::game::{SafeGameInterface, GameState, Rules};
 use crate::play::Play;
-use crate::card::entire_deck;
+use crate::card::{Card, entire_deck};
+use crate::runner::{run_game, OnDecision};
 
 const FITNESS_GAMES_TO_CHECK: usize = 5;
 const GAMES_PER_CPU: usize = 1;
@@ -152,27 +156,43 @@ fn get_data_from_one_game(current_parameters: [f64; N_PARAMETERS]) -> Vec<(Play,
 }
 
 fn play_by_play(players: Vec<Box<Player>>) -> Vec<Play> {
+    play_game(&players, |_, _| {}).get_record().to_vec()
+}
+
+/// Plays out a single game between `players` on a freshly shuffled deck.
+/// `on_play` sees every decision as it is made, along with what the deciding player could see
+pub fn play_game(
+    players: &[Box<dyn Player>],
+    on_play: impl FnMut(&SafeGameInterface, &Play),
+) -> GameState {
     let mut deck = entire_deck();
     deck.shuffle(&mut thread_rng());
 
-    let mut game = GameState::new(4, deck);
-
-    loop {
-        let current_player_index = game.current_player;
-        let current_player = &players[current_player_index];
+    let rules = Rules {
+        n_players: players.len(),
+        ..Rules::default()
+    };
 
-        let interface = SafeGameInterface::from_game(&game);
-        let play = current_player.choose_play(&interface);
+    play_game_on(players, rules, deck, on_play)
+}
 
-        game.play(play);
+/// Like `play_game`, but with the rules and the order of the deck chosen by the caller
+pub fn play_game_on(
+    players: &[Box<dyn Player>],
+    rules: Rules,
+    deck: Vec<Card>,
+    on_play: impl FnMut(&SafeGameInterface, &Play),
+) -> GameState {
+    let game = GameState::with_rules(rules, deck);
+    run_game(players, game, &mut [&mut OnDecision(on_play)])
+}
 
-        match game.winning_player() {
-            Some(_winning_player_id) => {
-                // want to record this
-                break game.get_record().to_vec();
-            }
-            None => {}, // game is still in progress
-        }
-    }
+pub fn load_parameters(path: impl AsRef<Path>) -> io::Result<[f64; N_PARAMETERS]> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
 
+pub fn save_parameters(parameters: &[f64; N_PARAMETERS], path: impl AsRef<Path>) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    Ok(serde_json::to_writer(writer, parameters)?)
 }
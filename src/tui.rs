@@ -0,0 +1,411 @@
+use crossterm::cursor::MoveTo;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{execute, queue};
+
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::process;
+
+use crate::bot::{HumanPlayer, Player};
+use crate::card::Card;
+use crate::game::SafeGameInterface;
+use crate::hint::{self, Hint};
+use crate::play::Play;
+
+// a full screen version of HumanPlayer.
+// the whole screen is redrawn after every key press, from what SafeGameInterface tells us
+
+const LOG_LINES: usize = 10;
+const HINTS_SHOWN: usize = 8;
+
+const HELP: &str =
+    "←/→ move  space pick  0-9 then space: pick by index  tab: next hint  enter: play  p: pass  q: quit";
+
+const ASSIST_HELP: &str = "h: pick the suggested play";
+
+pub struct TerminalPlayer {
+    pub assist: bool, // suggest plays, and warn before breaking up good combinations
+}
+
+impl Player for TerminalPlayer {
+    fn choose_play(&self, game: &SafeGameInterface) -> Play {
+        match self.run(game) {
+            Ok(Some(play)) => play,
+            Ok(None) => process::exit(0),
+            Err(e) => {
+                // without a working terminal, the plain prompts are better than nothing
+                eprintln!(
+                    "the full screen ui stopped working ({}), asking plainly instead",
+                    e
+                );
+                HumanPlayer.choose_play(game)
+            }
+        }
+    }
+}
+
+impl TerminalPlayer {
+    // None if they asked to quit
+    fn run(&self, game: &SafeGameInterface) -> io::Result<Option<Play>> {
+        let mut screen = Screen::new(game, self.assist);
+        let mut stdout = io::stdout();
+
+        let raw = RawMode::enable()?;
+
+        let play = loop {
+            screen.draw(&mut stdout)?;
+
+            let key = match event::read()? {
+                // windows also reports releases
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                _ => continue,
+            };
+
+            match screen.handle(key) {
+                Action::Continue => {}
+                Action::Submit(play) => break play,
+                Action::Quit => {
+                    drop(raw);
+                    execute!(stdout, ResetColor, Print("\n"))?;
+                    return Ok(None);
+                }
+            }
+        };
+
+        drop(raw);
+        execute!(stdout, ResetColor, Clear(ClearType::All), MoveTo(0, 0))?;
+
+        Ok(Some(play))
+    }
+}
+
+// raw mode for as long as this is around, so that the terminal goes back to normal even after a panic
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        let _ = execute!(io::stdout(), ResetColor);
+    }
+}
+
+enum Action {
+    Continue,
+    Submit(Play),
+    Quit,
+}
+
+struct Screen<'a, 'b> {
+    game: &'a SafeGameInterface<'b>,
+    hand: Vec<Card>,         // sorted
+    playable: HashSet<Card>, // cards that are part of at least one legal play
+    hints: Vec<Play>,        // legal plays, not including a pass
+    hint_index: Option<usize>,
+    cursor: usize,
+    selected: Vec<bool>,
+    typed_index: String,
+    message: String,
+    assist: Option<Hint>,
+    confirming: Option<Play>, // warned about, so playing it again goes ahead
+}
+
+impl<'a, 'b> Screen<'a, 'b> {
+    fn new(game: &'a SafeGameInterface<'b>, assist: bool) -> Screen<'a, 'b> {
+        let mut hand = game.my_hand().to_vec();
+        hand.sort();
+
+        let mut hints: Vec<Play> = game
+            .legal_plays()
+            .into_iter()
+            .filter(|p| !p.is_pass())
+            .collect();
+        hints.sort_by(|a, b| a.cards().len().cmp(&b.cards().len()).then(a.cmp(b)));
+
+        let playable = hints
+            .iter()
+            .flat_map(|p| p.cards().iter().cloned())
+            .collect();
+
+        Screen {
+            game,
+            selected: vec![false; hand.len()],
+            hand,
+            playable,
+            hints,
+            hint_index: None,
+            cursor: 0,
+            typed_index: String::new(),
+            message: String::new(),
+            assist: if assist { Some(hint::hint(game)) } else { None },
+            confirming: None,
+        }
+    }
+
+    fn pick(&mut self, play: &Play) {
+        for (card, selected) in self.hand.iter().zip(self.selected.iter_mut()) {
+            *selected = play.cards().contains(card);
+        }
+    }
+
+    fn submit(&mut self, play: Play) -> Action {
+        if self.assist.is_some() && self.confirming.as_ref() != Some(&play) {
+            let warnings = hint::warnings(self.game, &play);
+            if !warnings.is_empty() {
+                self.message = format!("{} (again to play it anyway)", warnings.join(", "));
+                self.confirming = Some(play);
+                return Action::Continue;
+            }
+        }
+        Action::Submit(play)
+    }
+
+    fn selection(&self) -> Vec<Card> {
+        self.hand
+            .iter()
+            .zip(self.selected.iter())
+            .filter(|&(_, &s)| s)
+            .map(|(&c, _)| c)
+            .collect()
+    }
+
+    fn toggle(&mut self, index: usize) {
+        if index < self.hand.len() {
+            self.selected[index] = !self.selected[index];
+            self.cursor = index;
+            self.hint_index = None;
+        } else {
+            self.message = format!("there is no card {}", index);
+        }
+    }
+
+    fn handle(&mut self, key: KeyEvent) -> Action {
+        self.message.clear();
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Action::Quit
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => {
+                self.cursor = (self.cursor + 1).min(self.hand.len().saturating_sub(1))
+            }
+            KeyCode::Char(digit) if digit.is_ascii_digit() => self.typed_index.push(digit),
+            KeyCode::Backspace => {
+                self.typed_index.pop();
+            }
+            KeyCode::Char(' ') => {
+                if self.typed_index.is_empty() {
+                    let cursor = self.cursor;
+                    self.toggle(cursor);
+                } else {
+                    let index = self.typed_index.parse().unwrap_or(usize::MAX);
+                    self.typed_index.clear();
+                    self.toggle(index);
+                }
+            }
+            KeyCode::Tab => {
+                if self.hints.is_empty() {
+                    self.message = "nothing beats the table, you'll have to pass".to_string();
+                } else {
+                    let next = self.hint_index.map_or(0, |i| (i + 1) % self.hints.len());
+                    self.hint_index = Some(next);
+
+                    let hint = self.hints[next].clone();
+                    self.pick(&hint);
+                }
+            }
+            KeyCode::Char('h') => {
+                if let Some(suggested) = self.assist.as_ref().map(|h| h.suggested.play.clone()) {
+                    self.pick(&suggested);
+                }
+            }
+            KeyCode::Char('p') => match self.game.can_play(vec![]) {
+                Ok(pass) => return self.submit(pass),
+                Err(e) => self.message = format!("you can't pass: {:?}", e),
+            },
+            KeyCode::Enter => {
+                if !self.typed_index.is_empty() {
+                    let index = self.typed_index.parse().unwrap_or(usize::MAX);
+                    self.typed_index.clear();
+                    self.toggle(index);
+                } else if self.selection().is_empty() {
+                    self.message = "pick some cards first, or press p to pass".to_string();
+                } else {
+                    match self.game.can_play(self.selection()) {
+                        Ok(play) => return self.submit(play),
+                        Err(e) => self.message = format!("you can't play that: {:?}", e),
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Action::Continue
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let me = self.game.my_seat();
+        let mut row = 0;
+
+        queue!(out, Clear(ClearType::All))?;
+
+        line(out, &mut row, &format!("pusoy - you are seat {}", me))?;
+        line(out, &mut row, "")?;
+
+        // the other players
+        let sizes = self.game.hand_sizes();
+        let others: Vec<String> = (0..self.game.n_players())
+            .filter(|&seat| seat != me)
+            .map(|seat| {
+                if self.game.is_finished(seat) {
+                    format!("seat {}: out", seat)
+                } else {
+                    format!("seat {}: {} cards", seat, sizes[seat])
+                }
+            })
+            .collect();
+        line(out, &mut row, &others.join("    "))?;
+
+        // the table
+        let table = match (self.game.get_play_on_table(), self.game.table_owner()) {
+            _ if self.game.is_first_turn() => "empty, you start (play the 3♣)".to_string(),
+            _ if self.game.have_control() => "you have control, play anything".to_string(),
+            (Some(play), Some(owner)) => format!("{}   (seat {})", play, owner),
+            _ => "empty".to_string(),
+        };
+        line(out, &mut row, &format!("table: {}", table))?;
+        line(out, &mut row, "")?;
+
+        // the log
+        let history: Vec<(usize, &Play)> = self.game.history().collect();
+        let start = history.len().saturating_sub(LOG_LINES);
+        for &(seat, play) in history[start..].iter() {
+            let who = if seat == me {
+                "you".to_string()
+            } else {
+                format!("seat {}", seat)
+            };
+            if play.is_pass() {
+                line(out, &mut row, &format!("  {} passed", who))?;
+            } else {
+                line(out, &mut row, &format!("  {} played {}", who, play))?;
+            }
+        }
+        for _ in history.len() - start..LOG_LINES {
+            line(out, &mut row, "")?;
+        }
+        line(out, &mut row, "")?;
+
+        // the hand
+        queue!(out, MoveTo(0, row), Print("hand: "))?;
+        for (i, card) in self.hand.iter().enumerate() {
+            if !self.playable.contains(card) {
+                queue!(out, SetForegroundColor(Color::DarkGrey))?;
+            }
+            if self.selected[i] {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            if i == self.cursor {
+                queue!(out, SetAttribute(Attribute::Underlined))?;
+            }
+            queue!(
+                out,
+                Print(format!("{}:{}", i, card)),
+                SetAttribute(Attribute::Reset),
+                ResetColor,
+                Print("  ")
+            )?;
+        }
+        row += 1;
+
+        // what we have picked so far
+        let selection = self.selection();
+        queue!(out, MoveTo(0, row), Print("picked: "))?;
+        if selection.is_empty() {
+            queue!(out, Print("nothing"))?;
+        } else {
+            let text: Vec<String> = selection.iter().map(|c| c.to_string()).collect();
+            match self.game.can_play(selection) {
+                Ok(play) => queue!(
+                    out,
+                    SetForegroundColor(Color::Green),
+                    Print(format!("{} ({:?})", text.join(" "), play.kind()))
+                )?,
+                Err(e) => queue!(
+                    out,
+                    SetForegroundColor(Color::DarkGrey),
+                    Print(format!("{} (can't play: {:?})", text.join(" "), e))
+                )?,
+            }
+            queue!(out, ResetColor)?;
+        }
+        row += 1;
+
+        if !self.typed_index.is_empty() {
+            queue!(out, Print(format!("   index: {}", self.typed_index)))?;
+        }
+
+        // hints
+        let hints: Vec<String> = self
+            .hints
+            .iter()
+            .take(HINTS_SHOWN)
+            .map(|p| p.to_string())
+            .collect();
+        let more = self.hints.len().saturating_sub(HINTS_SHOWN);
+        line(out, &mut row, "")?;
+        if hints.is_empty() {
+            line(out, &mut row, "legal: nothing, you have to pass")?;
+        } else if more > 0 {
+            line(
+                out,
+                &mut row,
+                &format!("legal: {}  (+{} more)", hints.join("  |  "), more),
+            )?;
+        } else {
+            line(out, &mut row, &format!("legal: {}", hints.join("  |  ")))?;
+        }
+
+        // the pass button
+        line(out, &mut row, "")?;
+        if self.game.can_play(vec![]).is_ok() {
+            line(out, &mut row, "[ p: pass ]")?;
+        } else {
+            queue!(out, SetForegroundColor(Color::DarkGrey))?;
+            line(out, &mut row, "[ p: pass ]")?;
+            queue!(out, ResetColor)?;
+        }
+
+        if let Some(hint) = &self.assist {
+            line(out, &mut row, "")?;
+            line(out, &mut row, &format!("hint: try {}", hint.suggested))?;
+            for alternative in hint.alternatives.iter() {
+                line(out, &mut row, &format!("        or {}", alternative))?;
+            }
+        }
+
+        line(out, &mut row, &self.message)?;
+        line(out, &mut row, HELP)?;
+        if self.assist.is_some() {
+            line(out, &mut row, ASSIST_HELP)?;
+        }
+
+        out.flush()
+    }
+}
+
+fn line(out: &mut impl Write, row: &mut u16, text: &str) -> io::Result<()> {
+    queue!(out, MoveTo(0, *row), Print(text))?;
+    *row += 1;
+    Ok(())
+}
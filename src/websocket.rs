@@ -0,0 +1,234 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha1_smol::Sha1;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+
+// just enough http and websocket (rfc 6455) for the browser client in web/index.html to talk to `server`.
+// every websocket text message carries one of the json messages from `protocol`
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// nothing in the protocol comes close to this
+const MAX_MESSAGE_LEN: u64 = 1 << 16;
+
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xA;
+
+/// Close codes, for `write_close`
+pub const NORMAL_CLOSURE: u16 = 1000;
+pub const UNSUPPORTED_DATA: u16 = 1003;
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    headers: HashMap<String, String>, // names are lowercase
+}
+
+impl Request {
+    /// Reads the request line and headers, leaving the body (if any) unread
+    pub fn read(input: &mut impl BufRead) -> io::Result<Request> {
+        let mut line = String::new();
+        input.read_line(&mut line)?;
+
+        let mut words = line.split_whitespace();
+        let method = words.next().ok_or_else(|| invalid("empty request"))?;
+        let path = words.next().ok_or_else(|| invalid("no path in request"))?;
+
+        let mut request = Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: HashMap::new(),
+        };
+
+        loop {
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                return Err(invalid("the headers never ended"));
+            }
+
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some(colon) = line.find(':') {
+                request.headers.insert(
+                    line[..colon].trim().to_ascii_lowercase(),
+                    line[colon + 1..].trim().to_string(),
+                );
+            }
+        }
+
+        Ok(request)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|s| s.as_str())
+    }
+
+    pub fn is_websocket(&self) -> bool {
+        self.header("upgrade")
+            .is_some_and(|u| u.eq_ignore_ascii_case("websocket"))
+    }
+}
+
+/// Finishes the opening handshake for a websocket request
+pub fn accept(out: &mut impl Write, request: &Request) -> io::Result<()> {
+    let key = request
+        .header("sec-websocket-key")
+        .ok_or_else(|| invalid("no Sec-WebSocket-Key"))?;
+
+    let mut sha = Sha1::new();
+    sha.update(key.as_bytes());
+    sha.update(ACCEPT_GUID.as_bytes());
+    let accept = STANDARD.encode(sha.digest().bytes());
+
+    write!(
+        out,
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    )?;
+    out.flush()
+}
+
+/// A plain http response, for everything that isn't a websocket
+pub fn respond(
+    out: &mut impl Write,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        out,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: {}\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    out.write_all(body)?;
+    out.flush()
+}
+
+pub enum Message {
+    Text(String),
+    Binary, // the protocol is all text, so the client should be closed with UNSUPPORTED_DATA
+    Close,
+}
+
+/// Reads the next whole message from a client, putting fragmented messages back together.
+/// Pings can come between the fragments, so `pong` is called with each one's data as it comes
+pub fn read_message(
+    input: &mut impl Read,
+    mut pong: impl FnMut(Vec<u8>) -> io::Result<()>,
+) -> io::Result<Message> {
+    let mut text = Vec::new();
+
+    loop {
+        let mut header = [0; 2];
+        match input.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(Message::Close),
+            Err(e) => return Err(e),
+        }
+
+        let fin = header[0] & 0x80 != 0;
+        let opcode = header[0] & 0x0F;
+        let masked = header[1] & 0x80 != 0;
+
+        let len = match header[1] & 0x7F {
+            126 => {
+                let mut bytes = [0; 2];
+                input.read_exact(&mut bytes)?;
+                u64::from(u16::from_be_bytes(bytes))
+            }
+            127 => {
+                let mut bytes = [0; 8];
+                input.read_exact(&mut bytes)?;
+                u64::from_be_bytes(bytes)
+            }
+            len => u64::from(len),
+        };
+
+        // clients always have to mask what they send
+        if !masked {
+            return Err(invalid("unmasked frame from a client"));
+        }
+        if len > MAX_MESSAGE_LEN - text.len() as u64 {
+            return Err(invalid("message too long"));
+        }
+
+        let mut mask = [0; 4];
+        input.read_exact(&mut mask)?;
+
+        let mut payload = vec![0; len as usize];
+        input.read_exact(&mut payload)?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+
+        match opcode {
+            TEXT | CONTINUATION => {
+                text.extend_from_slice(&payload);
+                if fin {
+                    return String::from_utf8(text)
+                        .map(Message::Text)
+                        .map_err(|_| invalid("message isn't utf-8"));
+                }
+            }
+            BINARY => return Ok(Message::Binary),
+            PING => pong(payload)?,
+            PONG => {}
+            CLOSE => return Ok(Message::Close),
+            _ => return Err(invalid("unknown opcode")),
+        }
+    }
+}
+
+pub fn write_text(out: &mut impl Write, text: &str) -> io::Result<()> {
+    write_frame(out, TEXT, text.as_bytes())
+}
+
+pub fn write_pong(out: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    write_frame(out, PONG, data)
+}
+
+pub fn write_close(out: &mut impl Write, code: u16) -> io::Result<()> {
+    write_frame(out, CLOSE, &code.to_be_bytes())
+}
+
+fn write_frame(out: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    // servers never mask, and we never need to fragment
+    let mut frame = vec![0x80 | opcode];
+
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= 0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    frame.extend_from_slice(payload);
+    out.write_all(&frame)?;
+    out.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}